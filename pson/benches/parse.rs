@@ -0,0 +1,60 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use pson::{ExprRef, PsonParser, PushParser};
+
+/// A document of many small records, the shape of a typical data dump.
+fn records(count: usize) -> String {
+    let mut text = String::from("[\n");
+    for i in 0..count {
+        text.push_str(&format!(
+            "  {{id {} name \"user {}\" email \"user{}@example.com\" score {}.5 active T tags [admin \"on call\"]}}\n",
+            i, i, i, i % 100
+        ));
+    }
+    text.push_str("]\n");
+    text
+}
+
+/// The best of a few runs, which is the least disturbed by other work.
+fn time(name: &str, bytes: usize, mut f: impl FnMut()) {
+    let mut best = Duration::MAX;
+    for _ in 0..5 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed());
+    }
+    let throughput = bytes as f64 / best.as_secs_f64() / 1e6;
+    println!("{:<24} {:>9.2} ms {:>8.1} MB/s", name, best.as_secs_f64() * 1e3, throughput);
+}
+
+fn main() {
+    let text = records(200_000);
+    let word = "a".repeat(20 << 20);
+    println!("records: {} bytes", text.len());
+    time("PsonParser", text.len(), || {
+        let mut parser = PsonParser::new(text.chars());
+        parser.parse().unwrap();
+        black_box(parser.get().unwrap());
+    });
+    time("PsonParser::from_bytes", text.len(), || {
+        let mut parser = PsonParser::from_bytes(text.as_bytes()).unwrap();
+        parser.parse().unwrap();
+        black_box(parser.get().unwrap());
+    });
+    time("ExprRef::parse", text.len(), || {
+        black_box(ExprRef::parse(&text).unwrap());
+    });
+    time("PushParser", text.len(), || {
+        let mut parser = PushParser::new();
+        for chunk in text.as_bytes().chunks(8192) {
+            parser.feed(chunk).unwrap();
+        }
+        black_box(parser.finish().unwrap());
+    });
+    time("long bareword", word.len(), || {
+        let mut parser = PsonParser::new(word.chars());
+        parser.parse().unwrap();
+        black_box(parser.get().unwrap());
+    });
+}
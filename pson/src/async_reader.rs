@@ -0,0 +1,75 @@
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+
+use futures_core::Stream;
+use futures_io::AsyncRead;
+
+use crate::error::PsonError;
+use crate::expr::Expr;
+use crate::options::ParseOptions;
+use crate::stream::PushParser;
+
+/// Asynchronous counterpart of [`crate::PsonReader`], parsing a document
+/// pulled from an [`AsyncRead`] without blocking the executor.
+pub struct AsyncPsonReader<R> {
+    reader: R,
+    parser: PushParser,
+    chunk: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncPsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        AsyncPsonReader::with_chunk_size(reader, 8192)
+    }
+    pub fn with_chunk_size(reader: R, size: usize) -> Self {
+        AsyncPsonReader {
+            reader,
+            parser: PushParser::new(),
+            chunk: vec![0; size.max(1)],
+        }
+    }
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.parser = self.parser.with_options(options);
+        self
+    }
+    pub async fn parse(mut self) -> Result<Expr, PsonError> {
+        loop {
+            let read = poll_fn(|cx| Pin::new(&mut self.reader).poll_read(cx, &mut self.chunk)).await;
+            let n = match read {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.parser.io_error(e)),
+            };
+            self.parser.feed(&self.chunk[..n])?;
+        }
+        self.parser.finish()
+    }
+}
+
+/// Parses a document arriving as a [`Stream`] of byte chunks, such as the
+/// frames of a socket.
+pub async fn parse_stream<S, B>(stream: S) -> Result<Expr, PsonError>
+where
+    S: Stream<Item = io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    parse_stream_with_options(stream, ParseOptions::new()).await
+}
+
+/// Like [`parse_stream`], with `options` such as limits for untrusted peers.
+pub async fn parse_stream_with_options<S, B>(mut stream: S, options: ParseOptions) -> Result<Expr, PsonError>
+where
+    S: Stream<Item = io::Result<B>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    let mut parser = PushParser::new().with_options(options);
+    while let Some(chunk) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+        match chunk {
+            Ok(bytes) => parser.feed(bytes.as_ref())?,
+            Err(e) => return Err(parser.io_error(e)),
+        }
+    }
+    parser.finish()
+}
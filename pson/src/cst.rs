@@ -0,0 +1,326 @@
+use std::{error::Error, fmt};
+
+use crate::error::PsonError;
+use crate::expr::Expr;
+use crate::lexer::{Lexer, TokenKind};
+use crate::scanner::PsonParser;
+use crate::writer;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// Whitespace between values, kept verbatim.
+    Whitespace(String),
+    /// A comment, delimiters included. A line comment ends before its line
+    /// break, which belongs to the whitespace after it.
+    Comment(String),
+    /// A bareword or quoted string exactly as written.
+    Scalar(String),
+    Array(Vec<Node>),
+    Map(Vec<Node>),
+}
+
+impl Node {
+    fn is_value(&self) -> bool {
+        matches!(self, Node::Scalar(_) | Node::Array(_) | Node::Map(_))
+    }
+    fn is_whitespace(&self) -> bool {
+        matches!(self, Node::Whitespace(_))
+    }
+    fn is_line_comment(&self) -> bool {
+        matches!(self, Node::Comment(text) if text.starts_with("//"))
+    }
+    fn write(&self, out: &mut String) {
+        match self {
+            Node::Whitespace(text) | Node::Comment(text) | Node::Scalar(text) => out.push_str(text),
+            Node::Array(children) => {
+                out.push('[');
+                children.iter().for_each(|child| child.write(out));
+                out.push(']');
+            }
+            Node::Map(children) => {
+                out.push('{');
+                children.iter().for_each(|child| child.write(out));
+                out.push('}');
+            }
+        }
+    }
+    fn source(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+    /// The decoded string a key node stands for.
+    fn key(&self) -> Option<String> {
+        match self {
+            Node::Scalar(text) => Lexer::new(text).next().map(|token| token.value.into_owned()),
+            _ => None,
+        }
+    }
+}
+
+fn build(text: &str) -> Vec<Node> {
+    let mut stack: Vec<(TokenKind, Vec<Node>)> = vec![(TokenKind::OpenBracket, Vec::new())];
+    for token in Lexer::new(text) {
+        let node = match token.kind {
+            TokenKind::Whitespace => Node::Whitespace(token.text.to_string()),
+            TokenKind::Comment => Node::Comment(token.text.to_string()),
+            TokenKind::Bareword | TokenKind::QuotedString | TokenKind::RawString | TokenKind::BlockString => Node::Scalar(token.text.to_string()),
+            TokenKind::OpenBracket | TokenKind::OpenBrace => {
+                stack.push((token.kind, Vec::new()));
+                continue;
+            }
+            TokenKind::Close => match stack.pop().expect("input was validated") {
+                (TokenKind::OpenBrace, children) => Node::Map(children),
+                (_, children) => Node::Array(children),
+            },
+        };
+        stack.last_mut().expect("input was validated").1.push(node);
+    }
+    stack.pop().expect("root frame").1
+}
+
+fn validate(text: &str) -> Result<(), PsonError> {
+    let mut parser = PsonParser::new(text.chars());
+    parser.parse()?;
+    parser.get()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'p> {
+    Key(&'p str),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    PathNotFound,
+    NotAMap,
+    DuplicateKey(String),
+    /// The replacement text is not exactly one valid PSON value. Comments
+    /// outside the value are refused too, as there is nowhere to keep them.
+    InvalidValue(Option<PsonError>),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::PathNotFound => write!(f, "path not found"),
+            EditError::NotAMap => write!(f, "path does not point at a map"),
+            EditError::DuplicateKey(key) => write!(f, "key `{}` already exists", key),
+            EditError::InvalidValue(Some(error)) => write!(f, "invalid value: {}", error),
+            EditError::InvalidValue(None) => write!(f, "invalid value: expected exactly one value and no comments around it"),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+/// Index of the `index`th value node.
+fn nth_value(children: &[Node], index: usize) -> Option<usize> {
+    children
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.is_value())
+        .nth(index)
+        .map(|(i, _)| i)
+}
+
+/// Indices of the key and value nodes of the first entry named `key`.
+fn find_entry(children: &[Node], key: &str) -> Option<(usize, usize)> {
+    let mut values = children.iter().enumerate().filter(|(_, node)| node.is_value());
+    while let (Some((k, key_node)), Some((v, _))) = (values.next(), values.next()) {
+        if key_node.key().as_deref() == Some(key) {
+            return Some((k, v));
+        }
+    }
+    None
+}
+
+/// The range of nodes holding the element at `segment`.
+fn locate(children: &[Node], is_map: bool, segment: Segment) -> Option<(usize, usize)> {
+    match (segment, is_map) {
+        (Segment::Index(index), false) => nth_value(children, index).map(|i| (i, i)),
+        (Segment::Key(key), true) => find_entry(children, key),
+        _ => None,
+    }
+}
+
+/// A lossless concrete syntax tree of a PSON document.
+///
+/// Printing it reproduces the source byte for byte, and edits only rewrite
+/// the nodes they touch, so whitespace and quoting elsewhere survive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    nodes: Vec<Node>,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Result<Self, PsonError> {
+        validate(text)?;
+        Ok(Document { nodes: build(text) })
+    }
+    fn parse_value(source: &str) -> Result<Node, EditError> {
+        validate(source).map_err(|e| EditError::InvalidValue(Some(e)))?;
+        let mut nodes = build(source).into_iter().filter(|node| !node.is_whitespace());
+        match (nodes.next(), nodes.next()) {
+            (Some(node), None) if node.is_value() => Ok(node),
+            _ => Err(EditError::InvalidValue(None)),
+        }
+    }
+    /// The children of the container at `path`, and whether it is a map.
+    fn container_mut(&mut self, path: &[Segment]) -> Result<(&mut Vec<Node>, bool), EditError> {
+        let (mut children, mut is_map) = (&mut self.nodes, false);
+        for &segment in path {
+            let (_, value) = locate(children, is_map, segment).ok_or(EditError::PathNotFound)?;
+            (children, is_map) = match &mut children[value] {
+                Node::Array(inner) => (inner, false),
+                Node::Map(inner) => (inner, true),
+                _ => return Err(EditError::PathNotFound),
+            };
+        }
+        Ok((children, is_map))
+    }
+    fn node_mut(&mut self, path: &[Segment]) -> Result<&mut Node, EditError> {
+        let (last, parent) = path.split_last().ok_or(EditError::PathNotFound)?;
+        let (children, is_map) = self.container_mut(parent)?;
+        let (_, value) = locate(children, is_map, *last).ok_or(EditError::PathNotFound)?;
+        Ok(&mut children[value])
+    }
+    /// The value at `path`; the empty path stands for the implicit root array.
+    pub fn get(&self, path: &[Segment]) -> Option<Expr> {
+        let (mut children, mut is_map, mut node) = (&self.nodes[..], false, None);
+        for &segment in path {
+            let (_, value) = locate(children, is_map, segment)?;
+            node = Some(&children[value]);
+            (children, is_map) = match &children[value] {
+                Node::Array(inner) => (&inner[..], false),
+                Node::Map(inner) => (&inner[..], true),
+                _ => (&[][..], false),
+            };
+        }
+        let Some(node) = node else {
+            return Some(self.to_expr());
+        };
+        match PsonParser::new(node.source().chars()).parse_recovering().0 {
+            Expr::Array(mut items) => items.pop(),
+            _ => None,
+        }
+    }
+    pub fn to_expr(&self) -> Expr {
+        PsonParser::new(self.to_string().chars()).parse_recovering().0
+    }
+    /// Replaces the value at `path` with `value`, given as PSON source.
+    /// Comments may appear inside a container value but not around it.
+    pub fn set(&mut self, path: &[Segment], value: &str) -> Result<(), EditError> {
+        let value = Document::parse_value(value)?;
+        *self.node_mut(path)? = value;
+        Ok(())
+    }
+    /// Appends a `key value` entry to the map at `path`, separating it from
+    /// the previous entry with the same whitespace that one is separated
+    /// from its own predecessor. Comments are left where they are, and the
+    /// entry goes after a comment trailing the last one on its line, on a
+    /// new line indented like the last key's if that comment is a line one.
+    pub fn insert(&mut self, path: &[Segment], key: &str, value: &str) -> Result<(), EditError> {
+        let value = Document::parse_value(value)?;
+        let (children, is_map) = self.container_mut(path)?;
+        if !is_map {
+            return Err(EditError::NotAMap);
+        }
+        if find_entry(children, key).is_some() {
+            return Err(EditError::DuplicateKey(key.to_string()));
+        }
+        let space = || Node::Whitespace(" ".to_string());
+        let entries: Vec<usize> = (0..children.len()).filter(|&i| children[i].is_value()).collect();
+        let mut nodes = Vec::with_capacity(4);
+        let (at, gap) = match *entries.as_slice() {
+            [] => (children.iter().take_while(|node| !node.is_value()).count(), space()),
+            [.., last_key, last_value] => {
+                let separator = last_key.checked_sub(1).map(|i| &children[i]).filter(|node| node.is_whitespace());
+                nodes.push(separator.cloned().unwrap_or_else(space));
+                let gap = match &children[last_key + 1..last_value] {
+                    [gap @ Node::Whitespace(_)] => gap.clone(),
+                    _ => space(),
+                };
+                let at = after_trailing_comment(children, last_value + 1);
+                // Nothing may follow a line comment on its own line: start a
+                // new one, indented like the line of the previous key.
+                if children[at - 1].is_line_comment() && !nodes[0].source().contains(['\n', '\r']) {
+                    nodes[0] = Node::Whitespace(format!("\n{}", line_indent(&children[..last_key])));
+                }
+                (at, gap)
+            }
+            [_] => unreachable!("validated maps have an even number of values"),
+        };
+        let mut key_text = String::new();
+        writer::write_string(&mut key_text, key).expect("writing to a String");
+        nodes.extend([Node::Scalar(key_text), gap, value]);
+        children.splice(at..at, nodes);
+        Ok(())
+    }
+    /// Removes the element at `path` (a whole entry for map keys) along with
+    /// the whitespace separating it from its neighbours. Comments are kept,
+    /// those between a key and its value included, and so is the line break
+    /// ending a line comment.
+    pub fn remove(&mut self, path: &[Segment]) -> Result<(), EditError> {
+        let (last, parent) = path.split_last().ok_or(EditError::PathNotFound)?;
+        let (children, is_map) = self.container_mut(parent)?;
+        let (mut start, mut end) = locate(children, is_map, *last).ok_or(EditError::PathNotFound)?;
+        let has_previous = children[..start].iter().any(Node::is_value);
+        let ends_comment = start >= 2 && children[start - 2].is_line_comment();
+        let leading = has_previous && children[start - 1].is_whitespace() && !ends_comment;
+        if leading {
+            start -= 1;
+        } else if children.get(end + 1).is_some_and(Node::is_whitespace) {
+            end += 1;
+        }
+        // A kept comment takes along the whitespace on the side the removed
+        // separator was on, and a line comment its line break as well.
+        let mut keep = vec![false; end + 1 - start];
+        for i in (start..=end).filter(|&i| matches!(children[i], Node::Comment(_))) {
+            keep[i - start] = true;
+            if leading && children[i - 1].is_whitespace() {
+                keep[i - 1 - start] = true;
+            }
+            if (!leading || children[i].is_line_comment()) && i < end && children[i + 1].is_whitespace() {
+                keep[i + 1 - start] = true;
+            }
+        }
+        let removed: Vec<Node> = children.drain(start..=end).collect();
+        let kept = removed.into_iter().zip(keep).filter_map(|(node, keep)| keep.then_some(node));
+        children.splice(start..start, kept);
+        Ok(())
+    }
+}
+
+/// The indentation of the line that `nodes`, the start of a container's
+/// children, end on. On the line of the opening bracket that is a single
+/// space, lining up with an entry right after the bracket.
+fn line_indent(nodes: &[Node]) -> String {
+    let mut source = String::new();
+    nodes.iter().for_each(|node| node.write(&mut source));
+    match source.rfind(['\n', '\r']) {
+        Some(newline) => source[newline + 1..].chars().take_while(|&c| c == ' ' || c == '\t').collect(),
+        None => " ".to_string(),
+    }
+}
+
+/// Where to add nodes after the value ending just before `at`: past a
+/// comment following it on the same line, which belongs with it.
+fn after_trailing_comment(children: &[Node], at: usize) -> usize {
+    let comment = match &children[at..] {
+        [Node::Whitespace(space), Node::Comment(_), ..] if !space.contains(['\n', '\r']) => at + 1,
+        [Node::Comment(_), ..] => at,
+        _ => return at,
+    };
+    comment + 1
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.nodes.iter().for_each(|node| node.write(&mut out));
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use crate::error::{ErrorKind, Position, PsonError};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders a [`PsonError`] against the source it came from, rustc style.
+pub struct Report<'a> {
+    error: &'a PsonError,
+    source: &'a str,
+    color: bool,
+}
+
+struct Label {
+    position: Position,
+    marker: char,
+    message: Option<String>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(error: &'a PsonError, source: &'a str) -> Self {
+        Report {
+            error,
+            source,
+            color: false,
+        }
+    }
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+    fn paint(&self, style: &'static str) -> (&'static str, &'static str) {
+        if self.color {
+            (style, RESET)
+        } else {
+            ("", "")
+        }
+    }
+    fn note(&self) -> Option<String> {
+        let what = match &self.error.kind {
+            ErrorKind::UnterminatedString => "string",
+            ErrorKind::UnterminatedComment => "comment",
+            ErrorKind::MismatchedBracket { expected, .. } | ErrorKind::UnclosedFrame(expected) => {
+                match expected {
+                    '}' => "map",
+                    _ => "array",
+                }
+            }
+            ErrorKind::OddMapEntries => "map",
+            ErrorKind::DuplicateKey(_) => return Some("first defined here".to_string()),
+            _ => return None,
+        };
+        Some(format!("{} opened here", what))
+    }
+    fn labels(&self) -> Vec<Label> {
+        let mut labels = vec![Label {
+            position: self.error.position,
+            marker: '^',
+            message: None,
+        }];
+        if let Some(opened) = self.error.opened_at {
+            labels.push(Label {
+                position: opened,
+                marker: '-',
+                message: self.note(),
+            });
+        }
+        labels.sort_by_key(|label| label.position.offset);
+        labels
+    }
+    fn source_line(&self, line: usize) -> &'a str {
+        let text = self.source.split('\n').nth(line - 1).unwrap_or("");
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (red, red_end) = self.paint(RED);
+        let (blue, blue_end) = self.paint(BLUE);
+        let (bold, bold_end) = self.paint(BOLD);
+        let labels = self.labels();
+        let width = labels
+            .iter()
+            .map(|label| label.position.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+        writeln!(f, "{}error{}{}: {}{}", red, red_end, bold, self.error.kind, bold_end)?;
+        writeln!(f, "{}{}-->{} {}", gutter, blue, blue_end, self.error.position)?;
+        writeln!(f, "{} {}|{}", gutter, blue, blue_end)?;
+        let mut last_line = None;
+        for label in &labels {
+            let line = label.position.line;
+            let text = self.source_line(line);
+            if last_line != Some(line) {
+                if last_line.is_some_and(|last| last + 1 < line) {
+                    writeln!(f, "{}{}...{}", gutter, blue, blue_end)?;
+                }
+                writeln!(f, "{}{:>width$} |{} {}", blue, line, blue_end, text)?;
+                last_line = Some(line);
+            }
+            let padding: String = text
+                .chars()
+                .take(label.position.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let (style, style_end) = if label.marker == '^' { (red, red_end) } else { (blue, blue_end) };
+            write!(f, "{} {}|{} {}{}{}", gutter, blue, blue_end, padding, style, label.marker)?;
+            if let Some(message) = &label.message {
+                write!(f, " {}", message)?;
+            }
+            writeln!(f, "{}", style_end)?;
+        }
+        Ok(())
+    }
+}
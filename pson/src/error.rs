@@ -0,0 +1,151 @@
+use std::{error::Error, fmt, io};
+
+use crate::diagnostic::Report;
+use crate::limits::Limit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub(crate) fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+    pub(crate) fn advance_str(&mut self, s: &str) {
+        self.offset += s.len();
+        // Most tokens are a few bytes long, where one plain pass is cheapest.
+        if s.len() <= 32 {
+            for &b in s.as_bytes() {
+                match b {
+                    b'\n' => {
+                        self.line += 1;
+                        self.column = 1;
+                    }
+                    // Continuation bytes belong to the character already counted.
+                    0x80..=0xBF => {}
+                    _ => self.column += 1,
+                }
+            }
+            return;
+        }
+        match s.rfind('\n') {
+            Some(last) => {
+                self.line += s.bytes().filter(|&b| b == b'\n').count();
+                self.column = 1 + s[last + 1..].chars().count();
+            }
+            None => self.column += s.chars().count(),
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape(String),
+    MismatchedBracket { expected: char, found: char },
+    UnexpectedClose(char),
+    OddMapEntries,
+    NonStringKey,
+    UnclosedFrame(char),
+    InvalidUtf8,
+    Io(io::ErrorKind),
+    /// A bareword starts like a number but does not follow the number
+    /// grammar; only reported in strict mode.
+    InvalidNumber(String),
+    /// An integer literal does not fit in an `i128`; only reported with
+    /// [`crate::IntegerOverflow::Error`].
+    IntegerOverflow(String),
+    /// A single value was expected but the input holds none.
+    MissingValue,
+    /// A single value was expected but another one follows it.
+    TrailingValue,
+    /// A key appears twice in the same map; `opened_at` points at the first one.
+    DuplicateKey(String),
+    /// The input goes beyond one of the configured [`crate::ParseLimits`].
+    LimitExceeded { limit: Limit, max: usize },
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            ErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            ErrorKind::InvalidEscape(escape) => write!(f, "invalid escape sequence `\\{}`", escape),
+            ErrorKind::MismatchedBracket { expected, found } => {
+                write!(f, "mismatched bracket: expected `{}`, found `{}`", expected, found)
+            }
+            ErrorKind::UnexpectedClose(c) => write!(f, "unexpected closing `{}`", c),
+            ErrorKind::OddMapEntries => write!(f, "map has a key without a value"),
+            ErrorKind::NonStringKey => write!(f, "map key must be a string"),
+            ErrorKind::UnclosedFrame(c) => write!(f, "unclosed frame: expected `{}`", c),
+            ErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ErrorKind::Io(kind) => write!(f, "I/O error: {}", kind),
+            ErrorKind::InvalidNumber(word) => write!(f, "invalid number `{}`", word),
+            ErrorKind::IntegerOverflow(word) => write!(f, "integer `{}` does not fit in 128 bits", word),
+            ErrorKind::MissingValue => write!(f, "expected a value, found end of input"),
+            ErrorKind::TrailingValue => write!(f, "unexpected value after the top-level value"),
+            ErrorKind::DuplicateKey(key) => write!(f, "duplicate map key `{}`", key),
+            ErrorKind::LimitExceeded { limit, max } => write!(f, "{} exceeds the limit of {}", limit, max),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsonError {
+    pub kind: ErrorKind,
+    pub position: Position,
+    /// Where the string or frame the error belongs to was opened, if any.
+    /// For a duplicate key, where the key first appeared.
+    pub opened_at: Option<Position>,
+}
+
+impl PsonError {
+    pub(crate) fn new(kind: ErrorKind, position: Position) -> Self {
+        PsonError {
+            kind,
+            position,
+            opened_at: None,
+        }
+    }
+    pub(crate) fn opened_at(mut self, position: Position) -> Self {
+        self.opened_at = Some(position);
+        self
+    }
+    pub fn report<'a>(&'a self, source: &'a str) -> Report<'a> {
+        Report::new(self, source)
+    }
+}
+
+impl fmt::Display for PsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.position)
+    }
+}
+
+impl Error for PsonError {}
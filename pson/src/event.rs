@@ -0,0 +1,96 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::str::Chars;
+
+use crate::error::{Position, PsonError};
+use crate::expr_ref::ExprRef;
+use crate::options::ParseOptions;
+use crate::scanner::Scanner;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartArray,
+    StartMap,
+    Key(Cow<'a, str>),
+    /// A non-container value; never holds `ExprRef::Array` or `ExprRef::Map`.
+    Scalar(ExprRef<'a>),
+    EndArray,
+    EndMap,
+}
+
+/// Receives events from the scanner as they are produced.
+pub(crate) trait Sink<'a> {
+    fn push(&mut self, event: Event<'a>, at: Position);
+}
+
+pub(crate) type EventQueue<'a> = VecDeque<(Event<'a>, Position)>;
+
+impl<'a> Sink<'a> for EventQueue<'a> {
+    fn push(&mut self, event: Event<'a>, at: Position) {
+        self.push_back((event, at));
+    }
+}
+
+/// Pull parser yielding [`Event`]s, each with the position where it starts,
+/// without materializing an [`crate::Expr`] tree. Strings borrow from the
+/// input whenever they need no unescaping.
+pub struct EventReader<'a> {
+    scanner: Scanner<'a, EventQueue<'a>>,
+    it: Chars<'a>,
+    error: Option<PsonError>,
+    done: bool,
+}
+
+impl<'a> EventReader<'a> {
+    pub fn new(text: Chars<'a>) -> EventReader<'a> {
+        EventReader {
+            scanner: Scanner::with_source(text.as_str(), EventQueue::new()),
+            it: text,
+            error: None,
+            done: false,
+        }
+    }
+    /// Of the duplicate key policies, only [`crate::DuplicateKeys::Error`]
+    /// makes a difference here: with any other, repeated keys are passed on
+    /// as they come.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.scanner.set_options(options);
+        self
+    }
+    pub fn position(&self) -> Position {
+        self.scanner.position()
+    }
+    fn advance(&mut self) -> Result<(), PsonError> {
+        let text = self.it.as_str();
+        if text.is_empty() {
+            self.done = true;
+            self.scanner.end()?;
+            return self.scanner.finish();
+        }
+        let fed = self.scanner.feed(text)?;
+        self.it = text[fed..].chars();
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for EventReader<'a> {
+    type Item = Result<(Event<'a>, Position), PsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.scanner.sink.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Some(error) = self.error.take() {
+                return Some(Err(error));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(error) = self.advance() {
+                self.done = true;
+                self.error = Some(error);
+            }
+        }
+    }
+}
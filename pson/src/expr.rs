@@ -1,4 +1,12 @@
-use std::{collections::HashMap, error::Error, hash::Hash};
+use std::{borrow::Cow, fmt, hash::Hash};
+
+use sha2::{Digest, Sha256};
+
+use crate::expr_ref::ExprRef;
+use crate::map::Map;
+use crate::number;
+use crate::pretty::PrettyPrinter;
+use crate::writer;
 
 #[derive(Debug)]
 pub enum Expr {
@@ -6,26 +14,21 @@ pub enum Expr {
     Boolean(bool),
     Integer(i128),
     Float(f64),
+    /// An integer too large for `i128`, kept as the literal it was written as.
+    /// Only produced with [`crate::IntegerOverflow::BigInt`].
+    BigInt(String),
+    /// A number with a fraction or an exponent, kept as the literal it was
+    /// written as. Only produced with exact decimals enabled in
+    /// [`crate::NumberOptions`].
+    Decimal(String),
     String(String),
     Array(Vec<Expr>),
-    Map(HashMap<String, Expr>),
+    Map(Map<String, Expr>),
 }
 
 impl Expr {
-    pub fn from(s: &String) -> Result<Self, Box<dyn Error>> {
-        if s == "N" {
-            Ok(Expr::Null())
-        } else if s == "T" {
-            Ok(Expr::Boolean(true))
-        } else if s == "F" {
-            Ok(Expr::Boolean(false))
-        } else if let Ok(n) = s.parse::<i128>() {
-            Ok(Expr::Integer(n))
-        } else if let Ok(n) = s.parse::<f64>() {
-            Ok(Expr::Float(n))
-        } else {
-            Ok(Expr::String(s.to_string()))
-        }
+    pub fn from(s: &str) -> Self {
+        ExprRef::from_bareword(Cow::Borrowed(s)).into_owned()
     }
     pub fn as_null(&self) -> Option<()> {
         match self {
@@ -51,6 +54,18 @@ impl Expr {
             _ => None,
         }
     }
+    pub fn as_big_int(&self) -> Option<String> {
+        match self {
+            Expr::BigInt(s) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+    pub fn as_decimal(&self) -> Option<String> {
+        match self {
+            Expr::Decimal(s) => Some(s.to_string()),
+            _ => None,
+        }
+    }
     pub fn as_string(&self) -> Option<String> {
         match self {
             Expr::String(s) => Some(s.to_string()),
@@ -63,14 +78,18 @@ impl Expr {
             _ => None,
         }
     }
-    pub fn as_map(&self) -> Option<HashMap<String, Expr>> {
+    pub fn as_map(&self) -> Option<Map<String, Expr>> {
         match self {
-            Expr::Map(m) => Some(m.to_owned()),
+            Expr::Map(m) => Some(m.clone()),
             _ => None,
         }
     }
 }
 
+/// Consistent with `PartialEq`: integral floats hash like the equal
+/// integer, and maps hash the same whatever their iteration order.
+/// Big integers and decimals compare and hash by their canonical text, so
+/// `0x10` and `16`, or `6.990` and `699e-2`, are equal.
 impl Hash for Expr {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
@@ -83,10 +102,13 @@ impl Hash for Expr {
                 state.write_u8(2);
                 state.write_i128(*n);
             }
-            Expr::Float(n) => {
-                state.write_u8(3);
-                state.write_u64(n.to_bits());
-            }
+            Expr::Float(n) => match writer::integral(*n) {
+                Some(n) => Expr::Integer(n).hash(state),
+                None => {
+                    state.write_u8(3);
+                    state.write_u64(n.to_bits());
+                }
+            },
             Expr::String(s) => {
                 state.write_u8(4);
                 state.write(s.as_bytes());
@@ -99,11 +121,27 @@ impl Hash for Expr {
             }
             Expr::Map(m) => {
                 state.write_u8(6);
-                for (k, v) in m {
+                let mut entries: Vec<_> = m.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                for (k, v) in entries {
                     k.hash(state);
                     v.hash(state);
                 }
             }
+            Expr::BigInt(s) => {
+                let s = number::canonical_integer(s);
+                match s.parse() {
+                    Ok(n) => Expr::Integer(n).hash(state),
+                    Err(_) => {
+                        state.write_u8(7);
+                        state.write(s.as_bytes());
+                    }
+                }
+            }
+            Expr::Decimal(s) => {
+                state.write_u8(8);
+                state.write(number::canonical_decimal(s).as_bytes());
+            }
         }
     }
 }
@@ -115,8 +153,11 @@ impl PartialEq for Expr {
             (Expr::Boolean(a), Expr::Boolean(b)) => a == b,
             (Expr::Integer(a), Expr::Integer(b)) => a == b,
             (Expr::Float(a), Expr::Float(b)) => a == b,
-            (Expr::Integer(a), Expr::Float(b)) => *a as f64 == *b,
-            (Expr::Float(a), Expr::Integer(b)) => *a == *b as f64,
+            // Exact: `as f64` would round large integers onto a nearby float.
+            (Expr::Integer(a), Expr::Float(b)) | (Expr::Float(b), Expr::Integer(a)) => writer::integral(*b) == Some(*a),
+            (Expr::BigInt(a), Expr::BigInt(b)) => a == b || number::canonical_integer(a) == number::canonical_integer(b),
+            (Expr::BigInt(a), Expr::Integer(b)) | (Expr::Integer(b), Expr::BigInt(a)) => number::canonical_integer(a) == b.to_string(),
+            (Expr::Decimal(a), Expr::Decimal(b)) => a == b || number::canonical_decimal(a) == number::canonical_decimal(b),
             (Expr::String(a), Expr::String(b)) => a == b,
             (Expr::Array(a), Expr::Array(b)) => a == b,
             (Expr::Map(a), Expr::Map(b)) => a == b,
@@ -132,6 +173,8 @@ impl Clone for Expr {
             Expr::Boolean(b) => Expr::Boolean(*b),
             Expr::Integer(n) => Expr::Integer(*n),
             Expr::Float(n) => Expr::Float(*n),
+            Expr::BigInt(s) => Expr::BigInt(s.to_string()),
+            Expr::Decimal(s) => Expr::Decimal(s.to_string()),
             Expr::String(s) => Expr::String(s.to_string()),
             Expr::Array(a) => Expr::Array(a.clone()),
             Expr::Map(m) => Expr::Map(m.clone()),
@@ -139,31 +182,27 @@ impl Clone for Expr {
     }
 }
 
-impl ToString for Expr {
-    fn to_string(&self) -> String {
-        match self {
-            Expr::Null() => "N".to_string(),
-            Expr::Boolean(b) => match b {
-                true => "T".to_string(),
-                false => "F".to_string(),
-            },
-            Expr::Integer(n) => n.to_string(),
-            Expr::Float(n) => n.to_string(),
-            Expr::String(s) => s.clone(),
-            Expr::Array(a) => format!(
-                "[{}]",
-                a.iter()
-                .map(|e| e.to_string())
-                .collect::<Vec<String>>()
-                .join(" "),
-            ),
-            Expr::Map(m) => format!(
-                "({})",
-                m.iter()
-                .map(|(k, v)| format!("{} {}", k, v.to_string()))
-                .collect::<Vec<String>>()
-                .join(" ")
-            ),
-        }
+impl Expr {
+    /// Lays the value out over several lines with the default [`PrettyPrinter`].
+    pub fn to_pretty_string(&self) -> String {
+        PrettyPrinter::new().print(self)
+    }
+    /// The compact form with sorted map keys and floats holding integers
+    /// written as integers, so that equal values always print the same.
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        writer::write_canonical(&mut out, self).expect("writing to a String");
+        out
+    }
+    /// SHA-256 of the canonical form, stable across runs and platforms.
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(self.to_canonical_string().as_bytes()).into()
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Writes the compact form, which parses back to an equal value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writer::write_compact(f, self)
     }
 }
@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+
+use crate::error::{ErrorKind, PsonError};
+use crate::expr::Expr;
+use crate::frame::TreeBuilder;
+use crate::map::Map;
+use crate::number::{parse_number, to_float, IntegerOverflow, Number, NumberOptions};
+use crate::scanner::Scanner;
+
+/// A borrowed counterpart of [`Expr`] whose strings point into the parsed
+/// text whenever they need no unescaping.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprRef<'a> {
+    Null(),
+    Boolean(bool),
+    Integer(i128),
+    Float(f64),
+    /// See [`Expr::BigInt`].
+    BigInt(Cow<'a, str>),
+    /// See [`Expr::Decimal`].
+    Decimal(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Array(Vec<ExprRef<'a>>),
+    Map(Map<Cow<'a, str>, ExprRef<'a>>),
+}
+
+impl<'a> ExprRef<'a> {
+    /// Parses `text` into the implicit root array, like [`crate::PsonParser::get`].
+    pub fn parse(text: &'a str) -> Result<Self, PsonError> {
+        let mut scanner = Scanner::with_source(text, TreeBuilder::new());
+        scanner.push_str(text)?;
+        scanner.end()?;
+        scanner.finish()?;
+        Ok(scanner.sink.get())
+    }
+    /// Interprets an unquoted word: `N`, `T`, `F`, numbers, or else a string.
+    ///
+    /// Numbers follow this grammar, with `_` allowed between digits:
+    ///
+    /// ```text
+    /// number   = [sign] (decimal | radix) | [sign] ".inf" | ".nan"
+    /// decimal  = integer ["." digits] [("e" | "E") [sign] digits]
+    /// integer  = "0" | nonzero {["_"] digit}
+    /// radix    = "0x" hexdigits | "0o" octdigits | "0b" bindigits
+    /// ```
+    ///
+    /// Decimals without a fraction or exponent and radix literals are
+    /// integers, unless they overflow an `i128`. Floats out of range are not
+    /// numbers, so neither `inf` nor `1e400` is; words like these that start
+    /// like a number are errors in strict mode.
+    pub fn from_bareword(s: Cow<'a, str>) -> Self {
+        ExprRef::from_bareword_with(s, NumberOptions::new()).0
+    }
+    /// Like [`ExprRef::from_bareword`], with `numbers` deciding what integers
+    /// beyond `i128` and decimals are read as. An overflow the options make
+    /// an error reads as the float it would be otherwise.
+    pub fn from_bareword_with_numbers(s: Cow<'a, str>, numbers: NumberOptions) -> Self {
+        ExprRef::from_bareword_with(s, numbers).0
+    }
+    /// Like [`ExprRef::from_bareword_with_numbers`], also returning the
+    /// overflow error the options call for.
+    pub(crate) fn from_bareword_with(s: Cow<'a, str>, numbers: NumberOptions) -> (Self, Option<ErrorKind>) {
+        let float = |s: Cow<'a, str>| match to_float(&s) {
+            n if n.is_finite() => ExprRef::Float(n),
+            _ => ExprRef::String(s),
+        };
+        let value = match s.as_ref() {
+            "N" => ExprRef::Null(),
+            "T" => ExprRef::Boolean(true),
+            "F" => ExprRef::Boolean(false),
+            _ => match parse_number(&s) {
+                Some(Number::Integer(n)) => ExprRef::Integer(n),
+                Some(Number::Float(n)) => ExprRef::Float(n),
+                Some(Number::BigInteger) => match numbers.integer_overflow {
+                    IntegerOverflow::Float => float(s),
+                    IntegerOverflow::BigInt => ExprRef::BigInt(s),
+                    IntegerOverflow::Error => {
+                        let error = ErrorKind::IntegerOverflow(s.to_string());
+                        return (float(s), Some(error));
+                    }
+                },
+                Some(Number::Decimal) if numbers.exact_decimals => ExprRef::Decimal(s),
+                Some(Number::Decimal) => float(s),
+                None => ExprRef::String(s),
+            },
+        };
+        (value, None)
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ExprRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn into_owned(self) -> Expr {
+        match self {
+            ExprRef::Null() => Expr::Null(),
+            ExprRef::Boolean(b) => Expr::Boolean(b),
+            ExprRef::Integer(n) => Expr::Integer(n),
+            ExprRef::Float(n) => Expr::Float(n),
+            ExprRef::BigInt(s) => Expr::BigInt(s.into_owned()),
+            ExprRef::Decimal(s) => Expr::Decimal(s.into_owned()),
+            ExprRef::String(s) => Expr::String(s.into_owned()),
+            ExprRef::Array(a) => Expr::Array(a.into_iter().map(ExprRef::into_owned).collect()),
+            ExprRef::Map(m) => Expr::Map(
+                m.into_iter()
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect()
+            ),
+        }
+    }
+}
@@ -1,45 +1,192 @@
-use std::{error::Error, collections::HashMap};
+use std::{borrow::Cow, hash::Hash};
 
+use crate::error::Position;
+use crate::event::{Event, Sink};
 use crate::expr::Expr;
+use crate::expr_ref::ExprRef;
+use crate::map::Map;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum FrameKind {
     Array,
     Map,
 }
 
+impl FrameKind {
+    pub(crate) fn closer(&self) -> char {
+        match self {
+            FrameKind::Array => ']',
+            FrameKind::Map => '}',
+        }
+    }
+}
+
+/// What a parser does with a key that appears more than once in a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail with [`crate::ErrorKind::DuplicateKey`], pointing at both occurrences.
+    #[default]
+    Error,
+    FirstWins,
+    /// The last value wins, but the key keeps its first position.
+    LastWins,
+    /// A repeated key maps to an array of all its values, in source order.
+    Collect,
+}
+
+/// Builds map entries out of `entries`, in source order, resolving repeated
+/// keys by `policy`. Past the scanner, `Error` only happens while recovering,
+/// where the first occurrence is kept.
+fn merge_entries<'a, K: Hash + Eq + Clone, N: Node<'a>>(entries: Vec<(K, N)>, policy: DuplicateKeys) -> Map<K, N> {
+    let entries = match Map::from_distinct(entries) {
+        Ok(map) => return map,
+        Err(entries) => entries,
+    };
+    let mut map = Map::new();
+    match policy {
+        DuplicateKeys::Error | DuplicateKeys::FirstWins => {
+            for (key, value) in entries {
+                if !map.contains_key(&key) {
+                    map.insert(key, value);
+                }
+            }
+        }
+        DuplicateKeys::LastWins => map.extend(entries),
+        DuplicateKeys::Collect => {
+            let mut collected: Map<K, Vec<N>> = Map::new();
+            for (key, value) in entries {
+                match collected.get_mut(&key) {
+                    Some(values) => values.push(value),
+                    None => {
+                        collected.insert(key, vec![value]);
+                    }
+                }
+            }
+            for (key, mut values) in collected {
+                let value = match values.len() {
+                    1 => values.pop().expect("one value"),
+                    _ => N::array(values),
+                };
+                map.insert(key, value);
+            }
+        }
+    }
+    map
+}
+
+/// A tree type the [`TreeBuilder`] can produce.
+pub(crate) trait Node<'a>: Sized {
+    fn scalar(scalar: ExprRef<'a>) -> Self;
+    fn array(items: Vec<Self>) -> Self;
+    /// Entries come in source order; repeated keys are resolved by `policy`.
+    fn map(entries: Vec<(Cow<'a, str>, Self)>, policy: DuplicateKeys) -> Self;
+}
+
+impl<'a> Node<'a> for Expr {
+    fn scalar(scalar: ExprRef<'a>) -> Self {
+        scalar.into_owned()
+    }
+    fn array(items: Vec<Self>) -> Self {
+        Expr::Array(items)
+    }
+    fn map(entries: Vec<(Cow<'a, str>, Self)>, policy: DuplicateKeys) -> Self {
+        let entries = entries.into_iter().map(|(key, value)| (key.into_owned(), value)).collect();
+        Expr::Map(merge_entries(entries, policy))
+    }
+}
+
+impl<'a> Node<'a> for ExprRef<'a> {
+    fn scalar(scalar: ExprRef<'a>) -> Self {
+        scalar
+    }
+    fn array(items: Vec<Self>) -> Self {
+        ExprRef::Array(items)
+    }
+    fn map(entries: Vec<(Cow<'a, str>, Self)>, policy: DuplicateKeys) -> Self {
+        ExprRef::Map(merge_entries(entries, policy))
+    }
+}
+
+/// An open container. Its items live on the builder's shared stacks from
+/// `start` on, so a container costs one exactly sized allocation when it
+/// closes rather than a growing buffer of its own.
 #[derive(Debug)]
-pub(crate) struct Frame {
-    exprs: Vec<Expr>,
-    pub(crate) kind: FrameKind
+struct Frame<'a> {
+    kind: FrameKind,
+    start: usize,
+    /// The key awaiting its value, in a map.
+    key: Option<Cow<'a, str>>,
 }
 
-impl Frame {
-    pub(crate) fn new(kind: FrameKind) -> Self {
-        Self{
-            exprs: Vec::new(),
-            kind
+/// Builds a tree out of a well-formed event stream.
+pub(crate) struct TreeBuilder<'a, N> {
+    frame_stack: Vec<Frame<'a>>,
+    /// Values of the open arrays, innermost last; the root array is at the bottom.
+    items: Vec<N>,
+    /// Entries of the open maps, innermost last.
+    entries: Vec<(Cow<'a, str>, N)>,
+    pub(crate) duplicate_keys: DuplicateKeys,
+}
+
+impl<'a, N: Node<'a>> TreeBuilder<'a, N> {
+    pub(crate) fn new() -> Self {
+        TreeBuilder {
+            frame_stack: vec![Frame{ kind: FrameKind::Array, start: 0, key: None }],
+            items: Vec::new(),
+            entries: Vec::new(),
+            duplicate_keys: DuplicateKeys::default(),
         }
     }
-    pub(crate) fn push(self: &mut Frame, expr: Expr) {
-        self.exprs.push(expr);
+    fn top(&mut self) -> &mut Frame<'a> {
+        self.frame_stack.last_mut().expect("root frame is never popped")
+    }
+    fn open(&mut self, kind: FrameKind) {
+        let start = match kind {
+            FrameKind::Array => self.items.len(),
+            FrameKind::Map => self.entries.len(),
+        };
+        self.frame_stack.push(Frame{ kind, start, key: None });
     }
-    pub(crate) fn to_array(self: Frame) -> Result<Expr, Box<dyn Error>> {
-        Ok(Expr::Array(self.exprs))
+    fn push(&mut self, expr: N) {
+        match self.top().key.take() {
+            Some(key) => self.entries.push((key, expr)),
+            None => self.items.push(expr),
+        }
     }
-    pub(crate) fn to_map(self: &mut Frame) -> Result<Expr, Box<dyn Error>> {
-        let mut map: HashMap<String, Expr> = HashMap::new();
-        while let Some(value) = self.exprs.pop() {
-            if let Some(key) = self.exprs.pop() {
-                let key_str = match key {
-                    Expr::String(s) => s,
-                    _ => Err("invalid map")?,
+    pub(crate) fn event(&mut self, event: Event<'a>) {
+        match event {
+            Event::StartArray => self.open(FrameKind::Array),
+            Event::StartMap => self.open(FrameKind::Map),
+            Event::Key(key) => self.top().key = Some(key),
+            Event::Scalar(expr) => self.push(N::scalar(expr)),
+            Event::EndArray | Event::EndMap => {
+                let frame = self.frame_stack.pop().expect("scanner balances frames");
+                let expr = match frame.kind {
+                    FrameKind::Array => N::array(self.items.drain(frame.start..).collect()),
+                    FrameKind::Map => N::map(self.entries.drain(frame.start..).collect(), self.duplicate_keys),
                 };
-                map.insert(key_str, value);
-            } else {
-                Err("invalid map")?;
+                self.push(expr);
             }
         }
-        Ok(Expr::Map(map))
+    }
+    /// Number of open frames, counting the root.
+    pub(crate) fn depth(&self) -> usize {
+        self.frame_stack.len()
+    }
+    /// Takes the most recently completed top-level value, if any.
+    pub(crate) fn pop_value(&mut self) -> Option<N> {
+        debug_assert_eq!(self.frame_stack.len(), 1);
+        self.items.pop()
+    }
+    /// Takes the implicit root array, leaving a fresh one in its place.
+    pub(crate) fn get(&mut self) -> N {
+        debug_assert_eq!(self.frame_stack.len(), 1);
+        N::array(std::mem::take(&mut self.items))
+    }
+}
+
+impl<'a, N: Node<'a>> Sink<'a> for TreeBuilder<'a, N> {
+    fn push(&mut self, event: Event<'a>, _: Position) {
+        self.event(event);
     }
 }
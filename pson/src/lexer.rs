@@ -0,0 +1,658 @@
+use std::{borrow::Cow, collections::VecDeque, ops::Range};
+
+use crate::error::{ErrorKind, Position, PsonError};
+use crate::memscan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    OpenBracket,
+    OpenBrace,
+    /// Either `]` or `}`; the token text tells which.
+    Close,
+    Bareword,
+    QuotedString,
+    /// `r"..."`, or `r#"..."#` with any number of `#`: no escapes at all.
+    RawString,
+    /// `"""` on to the next `"""`, without escapes and with the common
+    /// indentation of its lines stripped.
+    ///
+    /// Three quotes always open one, so `""""` no longer reads as two empty
+    /// strings but as an unterminated block string; write `"" ""` instead.
+    BlockString,
+    Whitespace,
+    /// A `// line comment` or a `/* block comment */`, which may nest.
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+    /// The token exactly as written in the source.
+    pub text: &'a str,
+    /// The token content with quotes removed and escapes processed.
+    pub value: Cow<'a, str>,
+}
+
+/// A token as produced by the [`Tokenizer`], detached from any source text.
+#[derive(Debug)]
+pub(crate) struct RawToken {
+    pub(crate) kind: TokenKind,
+    pub(crate) start: Position,
+    pub(crate) end: Position,
+    /// The first character of the token, which tells `]` from `}`.
+    pub(crate) lead: char,
+    /// Decoded content of barewords and strings, empty otherwise. A lazy
+    /// tokenizer only fills it from the first escape of a quoted string on.
+    pub(crate) value: String,
+    /// Offset of the first backslash of a quoted string, if it has one.
+    pub(crate) escaped: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Start,
+    Bareword,
+    Whitespace,
+    Quoted,
+    /// Right after `""`, which may be an empty string or open a block string.
+    EmptyQuoted,
+    /// `closing` counts the `#` after a `"` that may end the string.
+    RawString { hashes: usize, closing: Option<usize> },
+    /// `quotes` counts the `"` in a row that may end the string.
+    BlockString { quotes: usize },
+    Escape { escape: Position },
+    Hex { escape: Position, first: Option<char> },
+    /// Right after `\u`, expecting the opening brace.
+    UnicodeOpen { escape: Position },
+    /// Inside `\u{`; the digits so far are buffered from offset `digits`.
+    Unicode { escape: Position, digits: usize },
+    /// A `/` starting a token: either a comment or a bareword.
+    Slash,
+    LineComment,
+    /// `prev` is the previous character when it may pair with the next one.
+    BlockComment { depth: usize, prev: char },
+}
+
+/// Tokens the [`Tokenizer`] completes before its caller takes them.
+const BATCH: usize = 64;
+
+/// How far [`Tokenizer::whole_token`] looks for the end of a bareword;
+/// longer ones are left to the word-at-a-time scan of `skip_run`.
+const SHORT_WORD: usize = 64;
+
+/// The bytes that end a bareword.
+const DELIMITERS: [bool; 256] = {
+    let mut table = [false; 256];
+    let delimiters = b" \t\n\r[]{}\"";
+    let mut i = 0;
+    while i < delimiters.len() {
+        table[delimiters[i] as usize] = true;
+        i += 1;
+    }
+    table
+};
+
+pub(crate) fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+/// The character-at-a-time state machine splitting input into tokens.
+/// It never needs to look ahead, so it can be fed from any source.
+///
+/// When the caller keeps the source text around, the tokenizer can be made
+/// lazy: it then skips copying content that can be sliced out of the source
+/// instead, see [`token_value`].
+pub(crate) struct Tokenizer {
+    state: State,
+    buffer: String,
+    lazy: bool,
+    token_start: Position,
+    lead: char,
+    escaped: Option<usize>,
+    position: Position,
+    pub(crate) tokens: VecDeque<RawToken>,
+    /// While a bareword is `r` followed by this many `#`, the quote of a
+    /// raw string may follow.
+    raw_hashes: Option<usize>,
+    pub(crate) recover: bool,
+    /// Reports whitespace and comment tokens, which only the [`Lexer`] needs.
+    pub(crate) trivia: bool,
+    /// Makes unknown escapes an error rather than the escaped character.
+    pub(crate) strict: bool,
+    pub(crate) errors: Vec<PsonError>,
+}
+
+impl Tokenizer {
+    pub(crate) fn with_buffer_capacity(capacity: usize) -> Self {
+        Tokenizer {
+            state: State::Start,
+            buffer: String::with_capacity(capacity),
+            lazy: false,
+            token_start: Position::default(),
+            lead: '\0',
+            escaped: None,
+            position: Position::default(),
+            tokens: VecDeque::new(),
+            raw_hashes: None,
+            recover: false,
+            trivia: true,
+            strict: false,
+            errors: Vec::new(),
+        }
+    }
+    pub(crate) fn lazy() -> Self {
+        let mut tokenizer = Tokenizer::with_buffer_capacity(0);
+        tokenizer.lazy = true;
+        tokenizer
+    }
+    pub(crate) fn position(&self) -> Position {
+        self.position
+    }
+    /// Bytes buffered so far for the token in progress, and where it started.
+    pub(crate) fn pending(&self) -> (usize, Position) {
+        (self.buffer.len(), self.token_start)
+    }
+    pub(crate) fn report(&mut self, error: PsonError) -> Result<(), PsonError> {
+        if !self.recover {
+            return Err(error);
+        }
+        self.errors.push(error);
+        Ok(())
+    }
+    fn emit(&mut self, kind: TokenKind, end: Position) {
+        self.state = State::Start;
+        if !self.trivia && matches!(kind, TokenKind::Whitespace | TokenKind::Comment) {
+            self.buffer.clear();
+            return;
+        }
+        let mut value = std::mem::take(&mut self.buffer);
+        if !self.lazy && matches!(kind, TokenKind::RawString | TokenKind::BlockString) {
+            value = string_content(kind, &value).into_owned();
+        }
+        self.tokens.push_back(RawToken {
+            kind,
+            start: self.token_start,
+            end,
+            lead: self.lead,
+            value,
+            escaped: std::mem::take(&mut self.escaped),
+        });
+    }
+    /// Buffers `c` as part of the token text, which raw and block strings
+    /// are decoded from.
+    fn keep(&mut self, c: char) {
+        if !self.lazy {
+            self.buffer.push(c);
+        }
+    }
+    fn push_start(&mut self, c: char, start: Position) {
+        self.token_start = start;
+        self.lead = c;
+        self.raw_hashes = (c == 'r').then_some(0);
+        match c {
+            '[' => self.emit(TokenKind::OpenBracket, self.position),
+            '{' => self.emit(TokenKind::OpenBrace, self.position),
+            ']' | '}' => self.emit(TokenKind::Close, self.position),
+            '"' => self.state = State::Quoted,
+            '/' => {
+                self.state = State::Slash;
+                if !self.lazy {
+                    self.buffer.push(c);
+                }
+            }
+            c if is_whitespace(c) => self.state = State::Whitespace,
+            _ => {
+                self.state = State::Bareword;
+                if !self.lazy {
+                    self.buffer.push(c);
+                }
+            }
+        }
+    }
+    /// Reports a malformed escape, which is then kept as written; `text` is
+    /// the escape without its backslash.
+    fn invalid_escape(&mut self, escape: Position, text: String) -> Result<(), PsonError> {
+        self.report(PsonError::new(ErrorKind::InvalidEscape(text.clone()), escape))?;
+        self.buffer.push('\\');
+        self.buffer.push_str(&text);
+        Ok(())
+    }
+    /// Reports an escape cut short by the end of input.
+    fn unfinished_escape(&mut self) -> Result<(), PsonError> {
+        match self.state {
+            State::Hex { escape, first } => self.invalid_escape(escape, format!("x{}", first.map(String::from).unwrap_or_default())),
+            State::UnicodeOpen { escape } => self.invalid_escape(escape, "u".to_string()),
+            State::Unicode { escape, digits } => {
+                let hex = self.buffer.split_off(digits);
+                self.invalid_escape(escape, format!("u{{{}", hex))
+            }
+            _ => Ok(()),
+        }
+    }
+    fn push_quoted(&mut self, c: char, start: Position) {
+        match c {
+            '"' if start.offset == self.token_start.offset + 1 => self.state = State::EmptyQuoted,
+            '"' => self.emit(TokenKind::QuotedString, self.position),
+            '\\' => {
+                self.escaped.get_or_insert(start.offset);
+                self.state = State::Escape { escape: start };
+            }
+            _ => {
+                if !self.lazy || self.escaped.is_some() {
+                    self.buffer.push(c);
+                }
+            }
+        }
+    }
+    fn push_escaped(&mut self, c: char, escape: Position) -> Result<(), PsonError> {
+        self.state = State::Quoted;
+        match c {
+            'n' => self.buffer.push('\n'),
+            't' => self.buffer.push('\t'),
+            'r' => self.buffer.push('\r'),
+            '"' => self.buffer.push('"'),
+            '\\' => self.buffer.push('\\'),
+            'x' => self.state = State::Hex { escape, first: None },
+            'u' => self.state = State::UnicodeOpen { escape },
+            _ => {
+                if self.strict {
+                    self.report(PsonError::new(ErrorKind::InvalidEscape(c.to_string()), escape))?;
+                }
+                self.buffer.push(c);
+            }
+        }
+        Ok(())
+    }
+    fn push_hex(&mut self, c: char, start: Position, escape: Position, first: Option<char>) -> Result<(), PsonError> {
+        if !c.is_ascii_hexdigit() {
+            self.state = State::Quoted;
+            self.invalid_escape(escape, format!("x{}", first.map(String::from).unwrap_or_default()))?;
+            self.push_quoted(c, start);
+            return Ok(());
+        }
+        let Some(first) = first else {
+            self.state = State::Hex { escape, first: Some(c) };
+            return Ok(());
+        };
+        self.state = State::Quoted;
+        let n = first.to_digit(16).expect("hex digit") * 16 + c.to_digit(16).expect("hex digit");
+        // `\x` only spells ASCII; other characters are written `\u{..}`.
+        if n >= 0x80 {
+            return self.invalid_escape(escape, format!("x{}{}", first, c));
+        }
+        self.buffer.push(n as u8 as char);
+        Ok(())
+    }
+    fn push_unicode_open(&mut self, c: char, start: Position, escape: Position) -> Result<(), PsonError> {
+        if c == '{' {
+            self.state = State::Unicode { escape, digits: self.buffer.len() };
+            return Ok(());
+        }
+        self.state = State::Quoted;
+        self.invalid_escape(escape, "u".to_string())?;
+        self.push_quoted(c, start);
+        Ok(())
+    }
+    /// Takes up to six hex digits, which must name a Unicode scalar value:
+    /// surrogates and anything past U+10FFFF are rejected.
+    fn push_unicode(&mut self, c: char, start: Position, escape: Position, digits: usize) -> Result<(), PsonError> {
+        if c.is_ascii_hexdigit() && self.buffer.len() - digits < 6 {
+            self.buffer.push(c);
+            return Ok(());
+        }
+        self.state = State::Quoted;
+        let hex = self.buffer.split_off(digits);
+        if c != '}' {
+            self.invalid_escape(escape, format!("u{{{}", hex))?;
+            self.push_quoted(c, start);
+            return Ok(());
+        }
+        match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+            Some(decoded) => self.buffer.push(decoded),
+            None => self.invalid_escape(escape, format!("u{{{}}}", hex))?,
+        }
+        Ok(())
+    }
+    fn push_empty_quoted(&mut self, c: char, start: Position) {
+        if c != '"' {
+            self.emit(TokenKind::QuotedString, start);
+            return self.push_start(c, start);
+        }
+        self.state = State::BlockString { quotes: 0 };
+        if !self.lazy {
+            self.buffer.push_str("\"\"\"");
+        }
+    }
+    fn push_raw(&mut self, c: char, hashes: usize, closing: Option<usize>) {
+        self.keep(c);
+        let closing = match (c, closing) {
+            ('"', _) => Some(0),
+            ('#', Some(n)) => Some(n + 1),
+            _ => None,
+        };
+        match closing {
+            Some(n) if n == hashes => self.emit(TokenKind::RawString, self.position),
+            _ => self.state = State::RawString { hashes, closing },
+        }
+    }
+    fn push_block(&mut self, c: char, quotes: usize) {
+        self.keep(c);
+        match (c, quotes) {
+            ('"', 2) => self.emit(TokenKind::BlockString, self.position),
+            ('"', _) => self.state = State::BlockString { quotes: quotes + 1 },
+            _ => self.state = State::BlockString { quotes: 0 },
+        }
+    }
+    fn push_bareword(&mut self, c: char, start: Position) {
+        match (c, self.raw_hashes) {
+            ('"', Some(hashes)) => {
+                self.keep(c);
+                self.state = State::RawString { hashes, closing: None };
+                return;
+            }
+            ('#', Some(hashes)) => self.raw_hashes = Some(hashes + 1),
+            _ => self.raw_hashes = None,
+        }
+        if is_whitespace(c) || matches!(c, '[' | ']' | '{' | '}' | '"') {
+            self.emit(TokenKind::Bareword, start);
+            self.push_start(c, start);
+        } else if !self.lazy {
+            self.buffer.push(c);
+        }
+    }
+    fn push_slash(&mut self, c: char, start: Position) {
+        match c {
+            '/' => self.state = State::LineComment,
+            '*' => self.state = State::BlockComment { depth: 1, prev: '\0' },
+            _ => {
+                self.state = State::Bareword;
+                return self.push_bareword(c, start);
+            }
+        }
+        self.buffer.clear();
+    }
+    fn push_block_comment(&mut self, c: char, depth: usize, prev: char) {
+        self.state = match (prev, c) {
+            ('/', '*') => State::BlockComment { depth: depth + 1, prev: '\0' },
+            ('*', '/') if depth == 1 => return self.emit(TokenKind::Comment, self.position),
+            ('*', '/') => State::BlockComment { depth: depth - 1, prev: '\0' },
+            _ => State::BlockComment { depth, prev: c },
+        };
+    }
+    pub(crate) fn push(&mut self, c: char) -> Result<(), PsonError> {
+        let start = self.position;
+        self.position.advance(c);
+        match self.state {
+            State::Start => self.push_start(c, start),
+            State::Bareword => self.push_bareword(c, start),
+            State::Slash => self.push_slash(c, start),
+            State::LineComment => {
+                if matches!(c, '\n' | '\r') {
+                    self.emit(TokenKind::Comment, start);
+                    self.push_start(c, start);
+                }
+            }
+            State::BlockComment { depth, prev } => self.push_block_comment(c, depth, prev),
+            State::Whitespace => {
+                if !is_whitespace(c) {
+                    self.emit(TokenKind::Whitespace, start);
+                    self.push_start(c, start);
+                }
+            }
+            State::Quoted => self.push_quoted(c, start),
+            State::EmptyQuoted => self.push_empty_quoted(c, start),
+            State::RawString { hashes, closing } => self.push_raw(c, hashes, closing),
+            State::BlockString { quotes } => self.push_block(c, quotes),
+            State::Escape { escape } => self.push_escaped(c, escape)?,
+            State::Hex { escape, first } => self.push_hex(c, start, escape, first)?,
+            State::UnicodeOpen { escape } => self.push_unicode_open(c, start, escape)?,
+            State::Unicode { escape, digits } => self.push_unicode(c, start, escape, digits)?,
+        }
+        Ok(())
+    }
+    /// Consumes the start of `text` until a batch of tokens is complete,
+    /// returning how many bytes were taken. Plain content in between is
+    /// skipped over in runs, see [`Tokenizer::skip_run`].
+    ///
+    /// While recovering, a batch is a single token, so that errors found in
+    /// the tokens are collected in the order they appear.
+    pub(crate) fn feed(&mut self, text: &str) -> Result<usize, PsonError> {
+        let batch = if self.recover { 1 } else { BATCH };
+        let mut rest = text;
+        while self.tokens.len() < batch {
+            if let State::Start = self.state {
+                if let Some(len) = self.whole_token(rest) {
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            let Some(c) = rest.chars().next() else { break };
+            self.push(c)?;
+            rest = &rest[c.len_utf8()..];
+            let run = self.skip_run(rest);
+            rest = &rest[run..];
+        }
+        Ok(text.len() - rest.len())
+    }
+    /// Takes a whole token from the start of `rest` in one step, returning
+    /// its length, if it is a plain one that ends within `rest`. Comments,
+    /// escapes and raw or block strings are left to [`Tokenizer::push`].
+    fn whole_token(&mut self, rest: &str) -> Option<usize> {
+        let bytes = rest.as_bytes();
+        let (kind, len) = match *bytes.first()? {
+            b'[' => (TokenKind::OpenBracket, 1),
+            b'{' => (TokenKind::OpenBrace, 1),
+            b']' | b'}' => (TokenKind::Close, 1),
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                (TokenKind::Whitespace, bytes.iter().position(|&b| !is_whitespace(b as char))?)
+            }
+            b'"' => {
+                let len = 1 + memscan::find_any(&bytes[1..], b"\"\\");
+                if len == 1 || bytes.get(len) != Some(&b'"') {
+                    return None;
+                }
+                (TokenKind::QuotedString, len + 1)
+            }
+            b'/' => return None,
+            b'r' if matches!(bytes.get(1), Some(b'#' | b'"')) => return None,
+            _ => {
+                let end = bytes.iter().take(SHORT_WORD).position(|&b| DELIMITERS[b as usize])?;
+                (TokenKind::Bareword, end)
+            }
+        };
+        let text = &rest[..len];
+        self.token_start = self.position;
+        self.lead = text.chars().next().expect("tokens are not empty");
+        self.position.advance_str(text);
+        if !self.lazy {
+            match kind {
+                TokenKind::Bareword => self.buffer.push_str(text),
+                TokenKind::QuotedString => self.buffer.push_str(&text[1..len - 1]),
+                _ => {}
+            }
+        }
+        self.emit(kind, self.position);
+        Some(len)
+    }
+    /// Consumes the longest prefix of `rest` that cannot end the current
+    /// token, returning its length in bytes.
+    fn skip_run(&mut self, rest: &str) -> usize {
+        let (len, keep) = match self.state {
+            State::Whitespace => (rest.bytes().position(|b| !is_whitespace(b as char)).unwrap_or(rest.len()), false),
+            // A possible raw string prefix is followed char by char.
+            State::Bareword if self.raw_hashes.is_some() => return 0,
+            State::Bareword => (memscan::find_any(rest.as_bytes(), b" \t\n\r[]{}\""), !self.lazy),
+            State::RawString { closing: None, .. } | State::BlockString { quotes: 0 } => {
+                (memscan::find_any(rest.as_bytes(), b"\""), !self.lazy)
+            }
+            State::Quoted => (memscan::find_any(rest.as_bytes(), b"\"\\"), !self.lazy || self.escaped.is_some()),
+            State::LineComment => (memscan::find_any(rest.as_bytes(), b"\n\r"), false),
+            State::BlockComment { depth, .. } if !rest.is_empty() => {
+                let len = memscan::find_any(rest.as_bytes(), b"*/");
+                if len > 0 {
+                    self.state = State::BlockComment { depth, prev: '\0' };
+                }
+                (len, false)
+            }
+            _ => return 0,
+        };
+        let run = &rest[..len];
+        if keep {
+            self.buffer.push_str(run);
+        }
+        self.position.advance_str(run);
+        len
+    }
+    /// Signals the end of input, flushing the token in progress.
+    pub(crate) fn end(&mut self) -> Result<(), PsonError> {
+        match self.state {
+            State::Start => return Ok(()),
+            State::Bareword | State::Slash => self.emit(TokenKind::Bareword, self.position),
+            State::LineComment => self.emit(TokenKind::Comment, self.position),
+            State::BlockComment { .. } => {
+                let start = self.token_start;
+                self.report(PsonError::new(ErrorKind::UnterminatedComment, self.position).opened_at(start))?;
+                self.emit(TokenKind::Comment, self.position);
+            }
+            State::Whitespace => self.emit(TokenKind::Whitespace, self.position),
+            State::EmptyQuoted => self.emit(TokenKind::QuotedString, self.position),
+            State::RawString { .. } | State::BlockString { .. } => {
+                let kind = match self.state {
+                    State::RawString { .. } => TokenKind::RawString,
+                    _ => TokenKind::BlockString,
+                };
+                let quote = self.token_start;
+                self.report(PsonError::new(ErrorKind::UnterminatedString, self.position).opened_at(quote))?;
+                self.emit(kind, self.position);
+            }
+            State::Quoted | State::Escape { .. } | State::Hex { .. } | State::UnicodeOpen { .. } | State::Unicode { .. } => {
+                self.unfinished_escape()?;
+                let quote = self.token_start;
+                self.report(PsonError::new(ErrorKind::UnterminatedString, self.position).opened_at(quote))?;
+                self.emit(TokenKind::QuotedString, self.position);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The content of a raw or block string written as `text`, which lacks its
+/// closing delimiter if the string is unterminated.
+fn string_content(kind: TokenKind, text: &str) -> Cow<'_, str> {
+    if kind == TokenKind::BlockString {
+        let body = &text[3..];
+        return Cow::Owned(dedent(body.strip_suffix("\"\"\"").unwrap_or(body)));
+    }
+    let body = text[1..].trim_start_matches('#');
+    let closing = format!("\"{}", "#".repeat(text.len() - 1 - body.len()));
+    let body = &body[1..];
+    Cow::Borrowed(body.strip_suffix(closing.as_str()).unwrap_or(body))
+}
+
+/// Lays out the lines of a block string: a blank first line (the rest of
+/// the opening line) is dropped, as is a blank last line (the indentation
+/// of the closing delimiter), and the indentation common to the remaining
+/// lines and the closing delimiter is stripped.
+pub(crate) fn dedent(body: &str) -> String {
+    let is_blank = |line: &str| line.chars().all(|c| c == ' ' || c == '\t');
+    let mut lines: Vec<&str> = body.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect();
+    if lines.len() > 1 && is_blank(lines[0]) {
+        lines.remove(0);
+    }
+    let mut indent = usize::MAX;
+    if lines.len() > 1 && is_blank(lines[lines.len() - 1]) {
+        indent = lines.pop().expect("checked above").chars().count();
+    }
+    for line in lines.iter().filter(|line| !is_blank(line)) {
+        indent = indent.min(line.chars().take_while(|&c| c == ' ' || c == '\t').count());
+    }
+    let stripped: Vec<&str> = lines
+        .into_iter()
+        .map(|line| line.char_indices().nth(indent).map_or("", |(i, _)| &line[i..]))
+        .collect();
+    stripped.join("\n")
+}
+
+/// Recovers the decoded content of a token produced by a lazy [`Tokenizer`]
+/// fed with `source`, borrowing from it unless escapes had to be processed.
+pub(crate) fn token_value(source: &str, raw: RawToken) -> Cow<'_, str> {
+    let text = &source[raw.start.offset..raw.end.offset];
+    match raw.kind {
+        TokenKind::QuotedString => {}
+        TokenKind::RawString | TokenKind::BlockString => return string_content(raw.kind, text),
+        _ => return Cow::Borrowed(text),
+    }
+    match raw.escaped {
+        Some(escape) => {
+            let mut value = String::with_capacity(escape - raw.start.offset + raw.value.len());
+            value.push_str(&source[raw.start.offset + 1..escape]);
+            value.push_str(&raw.value);
+            Cow::Owned(value)
+        }
+        None => {
+            let inner = &text[1..];
+            Cow::Borrowed(inner.strip_suffix('"').unwrap_or(inner))
+        }
+    }
+}
+
+/// Splits PSON source into [`Token`]s covering every byte of the input,
+/// whitespace included.
+///
+/// Lexing never stops early: malformed strings still come out as tokens,
+/// and the problems found are available from [`Lexer::errors`].
+pub struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+    tokenizer: Tokenizer,
+    ended: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut tokenizer = Tokenizer::lazy();
+        tokenizer.recover = true;
+        Lexer {
+            source,
+            offset: 0,
+            tokenizer,
+            ended: false,
+        }
+    }
+    pub fn errors(&self) -> &[PsonError] {
+        &self.tokenizer.errors
+    }
+    fn token(&self, raw: RawToken) -> Token<'a> {
+        let span = raw.start.offset..raw.end.offset;
+        Token {
+            kind: raw.kind,
+            span: span.clone(),
+            text: &self.source[span],
+            value: token_value(self.source, raw),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(raw) = self.tokenizer.tokens.pop_front() {
+                return Some(self.token(raw));
+            }
+            if self.ended {
+                return None;
+            }
+            let rest = &self.source[self.offset..];
+            match rest.is_empty() {
+                false => self.offset += self.tokenizer.feed(rest).expect("lexer always recovers"),
+                true => {
+                    self.ended = true;
+                    self.tokenizer.end().expect("lexer always recovers");
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,45 @@
+#[cfg(feature = "async")]
+mod async_reader;
+mod cst;
+mod diagnostic;
+mod error;
+mod event;
 mod expr;
+mod expr_ref;
 mod frame;
+mod lexer;
+mod limits;
+mod lines;
+mod map;
+mod memscan;
+mod number;
+mod options;
+mod pretty;
 mod scanner;
+mod stream;
+mod values;
+mod writer;
 
+#[cfg(feature = "async")]
+pub use async_reader::{parse_stream, parse_stream_with_options, AsyncPsonReader};
+pub use cst::{Document, EditError, Segment};
+pub use diagnostic::Report;
+pub use error::{ErrorKind, Position, PsonError};
+pub use event::{Event, EventReader};
 pub use expr::Expr;
+pub use expr_ref::ExprRef;
+pub use frame::DuplicateKeys;
+pub use lexer::{Lexer, Token, TokenKind};
+pub use limits::{Limit, ParseLimits};
+pub use lines::{PsonLinesReader, PsonLinesWriter};
+pub use map::Map;
+pub use number::{IntegerOverflow, NumberOptions};
+pub use options::ParseOptions;
+pub use pretty::PrettyPrinter;
 pub use scanner::PsonParser;
+pub use stream::{PsonReader, PushParser};
+pub use values::Values;
+pub use writer::{PsonWriter, WriteError};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,74 @@
+use std::fmt;
+
+/// The quantity a [`ParseLimits`] bound applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Arrays and maps open at once, not counting the implicit root array.
+    Depth,
+    /// Bytes in a single string or bareword once unescaped.
+    StringBytes,
+    /// Items of one array or entries of one map.
+    CollectionLength,
+    /// Values and keys in the whole input.
+    Nodes,
+    /// Bytes in one line of a [`crate::PsonLinesReader`], line break included.
+    LineBytes,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Depth => write!(f, "nesting depth"),
+            Limit::StringBytes => write!(f, "string length in bytes"),
+            Limit::CollectionLength => write!(f, "collection length"),
+            Limit::Nodes => write!(f, "number of nodes"),
+            Limit::LineBytes => write!(f, "line length in bytes"),
+        }
+    }
+}
+
+/// Bounds on what a parser accepts, so that hostile input cannot make it
+/// use unbounded memory. Nothing is limited by default.
+///
+/// Exceeding a limit is always fatal, even while recovering from errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    pub(crate) max_depth: usize,
+    pub(crate) max_string_bytes: usize,
+    pub(crate) max_collection_len: usize,
+    pub(crate) max_nodes: usize,
+}
+
+impl ParseLimits {
+    pub fn new() -> Self {
+        ParseLimits {
+            max_depth: usize::MAX,
+            max_string_bytes: usize::MAX,
+            max_collection_len: usize::MAX,
+            max_nodes: usize::MAX,
+        }
+    }
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+    pub fn with_max_string_bytes(mut self, bytes: usize) -> Self {
+        self.max_string_bytes = bytes;
+        self
+    }
+    /// The implicit root array is only bounded by the number of nodes.
+    pub fn with_max_collection_len(mut self, len: usize) -> Self {
+        self.max_collection_len = len;
+        self
+    }
+    pub fn with_max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = nodes;
+        self
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits::new()
+    }
+}
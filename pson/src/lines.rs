@@ -0,0 +1,160 @@
+use std::io::{self, BufRead, Write};
+
+use crate::error::{ErrorKind, Position, PsonError};
+use crate::expr::Expr;
+use crate::limits::Limit;
+use crate::options::ParseOptions;
+use crate::scanner::PsonParser;
+
+/// Reads "PSON lines": one top-level value per line, ignoring lines that
+/// hold only whitespace and comments.
+///
+/// A malformed record yields an error positioned within the whole stream
+/// and reading carries on with the next line; I/O errors end the iteration.
+pub struct PsonLinesReader<R: BufRead> {
+    reader: R,
+    line: Vec<u8>,
+    /// Bytes the current line takes in the stream; more than `line` holds
+    /// when the line is over the limit.
+    line_len: usize,
+    start: Position,
+    line_number: usize,
+    options: ParseOptions,
+    max_line_bytes: usize,
+    done: bool,
+}
+
+impl<R: BufRead> PsonLinesReader<R> {
+    pub fn new(reader: R) -> Self {
+        PsonLinesReader {
+            reader,
+            line: Vec::new(),
+            line_len: 0,
+            start: Position::default(),
+            line_number: 0,
+            options: ParseOptions::new(),
+            max_line_bytes: usize::MAX,
+            done: false,
+        }
+    }
+    /// Sets the options every record is parsed with.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// Bounds the bytes of a line, line break included, so that a single
+    /// huge record cannot exhaust memory. A longer line is skipped without
+    /// being buffered and yields [`ErrorKind::LimitExceeded`] for
+    /// [`Limit::LineBytes`]. Lines are not limited by default.
+    pub fn with_max_line_bytes(mut self, bytes: usize) -> Self {
+        self.max_line_bytes = bytes;
+        self
+    }
+    /// The 1-based line number of the last record read.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+    fn relocate(&self, mut position: Position) -> Position {
+        position.offset += self.start.offset;
+        position.line += self.start.line - 1;
+        position
+    }
+    fn parse_line(&self) -> Result<Expr, PsonError> {
+        let record = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+        let record = record.strip_suffix(b"\r").unwrap_or(record);
+        PsonParser::from_bytes(record)
+            .and_then(|parser| parser.with_options(self.options).parse_value())
+            .map_err(|mut error| {
+                error.position = self.relocate(error.position);
+                error.opened_at = error.opened_at.map(|opened| self.relocate(opened));
+                error
+            })
+    }
+    /// Reads up to the next line break, keeping the line only while it is
+    /// within the limit, and returns how many bytes it takes in the stream.
+    fn read_line(&mut self) -> io::Result<usize> {
+        let mut len = 0;
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if available.is_empty() {
+                return Ok(len);
+            }
+            let (take, ended) = match available.iter().position(|&b| b == b'\n') {
+                Some(newline) => (newline + 1, true),
+                None => (available.len(), false),
+            };
+            len += take;
+            match len <= self.max_line_bytes {
+                true => self.line.extend_from_slice(&available[..take]),
+                false => self.line.clear(),
+            }
+            self.reader.consume(take);
+            if ended {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PsonLinesReader<R> {
+    type Item = Result<Expr, PsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.start.offset += self.line_len;
+            self.start.line = self.line_number + 1;
+            self.line.clear();
+            self.line_len = 0;
+            match self.read_line() {
+                Ok(0) => self.done = true,
+                Ok(len) => {
+                    self.line_len = len;
+                    self.line_number += 1;
+                    if len > self.max_line_bytes {
+                        let kind = ErrorKind::LimitExceeded { limit: Limit::LineBytes, max: self.max_line_bytes };
+                        return Some(Err(PsonError::new(kind, self.start)));
+                    }
+                    match self.parse_line() {
+                        // Only whitespace and comments, which hold no record.
+                        Err(error) if error.kind == ErrorKind::MissingValue => continue,
+                        record => return Some(record),
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(PsonError::new(ErrorKind::Io(e.kind()), self.start)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Writes "PSON lines", one record per line.
+pub struct PsonLinesWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        PsonLinesWriter { writer }
+    }
+    /// Writes `expr` in compact form followed by a newline. The compact form
+    /// escapes line breaks inside strings, so a record never spans lines.
+    pub fn write(&mut self, expr: &Expr) -> io::Result<()> {
+        let record = expr.to_string();
+        debug_assert!(!record.contains(['\n', '\r']));
+        self.writer.write_all(record.as_bytes())?;
+        self.writer.write_all(b"\n")
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,202 @@
+use std::{borrow::Borrow, collections::HashMap, fmt, hash::Hash, ops::Index};
+
+/// A map that remembers the order its keys were first inserted in.
+///
+/// Iteration and serialization follow that order; equality does not, so two
+/// maps with the same entries are equal whatever order they were written in.
+#[derive(Clone)]
+pub struct Map<K, V> {
+    entries: Vec<(K, V)>,
+    /// Only built once the map outgrows `LINEAR` entries, and empty until
+    /// then: small maps are searched entry by entry, which spares hashing
+    /// and a second copy of every key. Boxed, so that small maps, and the
+    /// values holding them, stay small.
+    #[allow(clippy::box_collection)]
+    index: Option<Box<HashMap<K, usize>>>,
+}
+
+const LINEAR: usize = 8;
+
+impl<K: Hash + Eq + Clone, V> Map<K, V> {
+    pub fn new() -> Self {
+        Map::with_capacity(0)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Map {
+            entries: Vec::with_capacity(capacity),
+            index: (capacity > LINEAR).then(|| Box::new(HashMap::with_capacity(capacity))),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    fn find<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+    {
+        match self.entries.len() > LINEAR {
+            true => self.index.as_ref().and_then(|index| index.get(key)).copied(),
+            false => self.entries.iter().position(|(k, _)| k.borrow() == key),
+        }
+    }
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.find(key).map(|i| &self.entries[i].1)
+    }
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        self.find(key).map(|i| &mut self.entries[i].1)
+    }
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.find(key).is_some()
+    }
+    /// Inserts `value` under `key`. An existing key keeps its position and
+    /// gets the new value, and the old one is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(i) = self.find(&key) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+        let i = self.entries.len();
+        match i {
+            LINEAR => {
+                let index = self.index.get_or_insert_with(Default::default);
+                index.extend(self.entries.iter().enumerate().map(|(i, (k, _))| (k.clone(), i)));
+                index.insert(key.clone(), i);
+            }
+            i if i > LINEAR => {
+                self.index.as_mut().expect("built at LINEAR entries").insert(key.clone(), i);
+            }
+            _ => {}
+        }
+        self.entries.push((key, value));
+        None
+    }
+    /// Takes `entries` in order if their keys are all distinct, or gives
+    /// them back otherwise.
+    pub(crate) fn from_distinct(entries: Vec<(K, V)>) -> Result<Self, Vec<(K, V)>> {
+        let mut index = None;
+        let distinct = match entries.len() > LINEAR {
+            true => {
+                let index = index.insert(Box::new(HashMap::with_capacity(entries.len())));
+                entries.iter().enumerate().all(|(i, (key, _))| index.insert(key.clone(), i).is_none())
+            }
+            false => entries
+                .iter()
+                .enumerate()
+                .all(|(i, (key, _))| entries[..i].iter().all(|(other, _)| other != key)),
+        };
+        match distinct {
+            true => Ok(Map { entries, index }),
+            false => Err(entries),
+        }
+    }
+    /// Removes `key`, shifting the entries after it to keep their order.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.find(key)?;
+        let (_, value) = self.entries.remove(i);
+        let Some(index) = &mut self.index else {
+            return Some(value);
+        };
+        if self.entries.len() <= LINEAR {
+            index.clear();
+            return Some(value);
+        }
+        index.remove(key);
+        for position in index.values_mut() {
+            if *position > i {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+    /// Empties the map, keeping its allocations for reuse.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        if let Some(index) = &mut self.index {
+            index.clear();
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Map::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: PartialEq> PartialEq for Map<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Hash + Eq + Clone + Borrow<Q>, Q: Hash + Eq + ?Sized, V> Index<&Q> for Map<K, V> {
+    type Output = V;
+
+    /// Panics if `key` is not present, like indexing a `HashMap`.
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in map")
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Map<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.entries.iter().map(|(k, v)| (k, v))).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> FromIterator<(K, V)> for Map<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Extend<(K, V)> for Map<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for Map<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'m, K, V> IntoIterator for &'m Map<K, V> {
+    type Item = (&'m K, &'m V);
+    type IntoIter = std::iter::Map<std::slice::Iter<'m, (K, V)>, fn(&'m (K, V)) -> (&'m K, &'m V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
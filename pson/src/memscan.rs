@@ -0,0 +1,27 @@
+const LO: u64 = 0x0101_0101_0101_0101;
+const HI: u64 = 0x8080_8080_8080_8080;
+
+/// Flags (possibly among false positives after a true hit) the bytes of
+/// `word` equal to `byte`.
+fn matches(word: u64, byte: u8) -> u64 {
+    let x = word ^ (LO * byte as u64);
+    x.wrapping_sub(LO) & !x & HI
+}
+
+/// Returns the index of the first byte of `bytes` that is one of `targets`,
+/// or `bytes.len()` if there is none. Scans eight bytes at a time.
+pub(crate) fn find_any(bytes: &[u8], targets: &[u8]) -> usize {
+    let mut chunks = bytes.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in chunks.by_ref() {
+        let word = u64::from_le_bytes(chunk.try_into().expect("chunk of eight"));
+        if targets.iter().any(|&t| matches(word, t) != 0) {
+            break;
+        }
+        offset += 8;
+    }
+    bytes[offset..]
+        .iter()
+        .position(|b| targets.contains(b))
+        .map_or(bytes.len(), |i| offset + i)
+}
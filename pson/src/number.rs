@@ -0,0 +1,232 @@
+use std::borrow::Cow;
+
+/// What an integer literal too large for an `i128` is read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerOverflow {
+    /// The nearest float, or a string if even that is out of range.
+    #[default]
+    Float,
+    /// An [`crate::Expr::BigInt`] holding the literal as written.
+    BigInt,
+    /// An [`crate::ErrorKind::IntegerOverflow`] error.
+    Error,
+}
+
+/// How a parser reads numbers that an `i128` or an `f64` cannot hold
+/// exactly. By default they become the nearest float.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumberOptions {
+    pub(crate) integer_overflow: IntegerOverflow,
+    pub(crate) exact_decimals: bool,
+}
+
+impl NumberOptions {
+    pub fn new() -> Self {
+        NumberOptions::default()
+    }
+    pub fn with_integer_overflow(mut self, overflow: IntegerOverflow) -> Self {
+        self.integer_overflow = overflow;
+        self
+    }
+    /// Reads numbers with a fraction or an exponent as [`crate::Expr::Decimal`]
+    /// instead of floats, so that `6.99` stays exactly `6.99`.
+    pub fn with_exact_decimals(mut self, exact: bool) -> Self {
+        self.exact_decimals = exact;
+        self
+    }
+}
+
+/// A bareword that follows the number grammar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Number {
+    Integer(i128),
+    /// An integer literal outside the range of `i128`.
+    BigInteger,
+    /// A literal with a fraction or an exponent.
+    Decimal,
+    /// `.inf`, `-.inf` or `.nan`.
+    Float(f64),
+}
+
+/// The digits of `s` in `radix` with separators removed, if `s` is a
+/// non-empty run of digits with single underscores between them.
+fn digits(s: &str, radix: u32) -> Option<Cow<'_, str>> {
+    // Starting from a separator rules out a leading one and an empty `s`.
+    let mut previous = b'_';
+    let mut separated = false;
+    for &b in s.as_bytes() {
+        match b {
+            b'_' if previous == b'_' => return None,
+            b'_' => separated = true,
+            _ if !(b as char).is_digit(radix) => return None,
+            _ => {}
+        }
+        previous = b;
+    }
+    match (previous, separated) {
+        (b'_', _) => None,
+        (_, true) => Some(Cow::Owned(s.replace('_', ""))),
+        (_, false) => Some(Cow::Borrowed(s)),
+    }
+}
+
+fn without_separators(s: &str) -> Cow<'_, str> {
+    match s.contains('_') {
+        true => Cow::Owned(s.replace('_', "")),
+        false => Cow::Borrowed(s),
+    }
+}
+
+/// Length of the leading run of `s` that may belong to a digit sequence.
+fn run(s: &str) -> usize {
+    s.find(|c: char| !c.is_ascii_digit() && c != '_').unwrap_or(s.len())
+}
+
+fn split_sign(s: &str) -> (bool, &str) {
+    match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    }
+}
+
+fn split_radix(body: &str) -> Option<(&str, u32)> {
+    [("0x", 16), ("0o", 8), ("0b", 2)]
+        .into_iter()
+        .find_map(|(prefix, radix)| Some((body.strip_prefix(prefix)?, radix)))
+}
+
+fn integer(negative: bool, digits: &str, radix: u32) -> Number {
+    // Most literals fit a `u64`, which is much cheaper to accumulate.
+    let magnitude = u64::from_str_radix(digits, radix).map(u128::from).or_else(|_| u128::from_str_radix(digits, radix));
+    let n = magnitude.ok().and_then(|magnitude| match negative {
+        true => 0i128.checked_sub_unsigned(magnitude),
+        false => i128::try_from(magnitude).ok(),
+    });
+    n.map_or(Number::BigInteger, Number::Integer)
+}
+
+/// Reads `s` as a number if it follows the grammar documented on
+/// [`crate::ExprRef::from_bareword`].
+pub(crate) fn parse_number(s: &str) -> Option<Number> {
+    if !s.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '.')) {
+        return None;
+    }
+    match s {
+        ".inf" | "+.inf" => return Some(Number::Float(f64::INFINITY)),
+        "-.inf" => return Some(Number::Float(f64::NEG_INFINITY)),
+        ".nan" => return Some(Number::Float(f64::NAN)),
+        _ => {}
+    }
+    let (negative, body) = split_sign(s);
+    if let Some((rest, radix)) = split_radix(body) {
+        return Some(integer(negative, &digits(rest, radix)?, radix));
+    }
+    let len = run(body);
+    let whole = digits(&body[..len], 10)?;
+    if whole.len() > 1 && whole.starts_with('0') {
+        return None;
+    }
+    let mut rest = &body[len..];
+    let mut decimal = false;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = run(fraction);
+        digits(&fraction[..len], 10)?;
+        rest = &fraction[len..];
+        decimal = true;
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let len = run(exponent);
+        digits(&exponent[..len], 10)?;
+        rest = &exponent[len..];
+        decimal = true;
+    }
+    match (rest.is_empty(), decimal) {
+        (false, _) => None,
+        (true, true) => Some(Number::Decimal),
+        (true, false) => Some(integer(negative, &whole, 10)),
+    }
+}
+
+/// The float nearest to `s`, which [`parse_number`] must have accepted.
+pub(crate) fn to_float(s: &str) -> f64 {
+    let (negative, body) = split_sign(s);
+    let magnitude = match split_radix(body) {
+        Some((rest, radix)) => rest
+            .chars()
+            .filter(|&c| c != '_')
+            .fold(0.0, |n: f64, c| n * radix as f64 + c.to_digit(radix).expect("validated digit") as f64),
+        None => without_separators(body).parse().expect("validated number syntax"),
+    };
+    if negative { -magnitude } else { magnitude }
+}
+
+/// Whether `s` starts like a number: a digit, or a point followed by one,
+/// optionally after a sign.
+pub(crate) fn looks_numeric(s: &str) -> bool {
+    let body = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let body = body.strip_prefix('.').unwrap_or(body);
+    body.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// The plain decimal form of an integer literal: no separators, radix
+/// prefix, plus sign or leading zeros, and `0` for zero. Text that is not
+/// an integer literal is returned as is.
+pub(crate) fn canonical_integer(s: &str) -> String {
+    if !matches!(parse_number(s), Some(Number::Integer(_) | Number::BigInteger)) {
+        return s.to_string();
+    }
+    let (negative, body) = split_sign(s);
+    let (rest, radix) = split_radix(body).unwrap_or((body, 10));
+    // Decimal digits, least significant first.
+    let mut decimal: Vec<u8> = Vec::new();
+    for c in rest.chars().filter(|&c| c != '_') {
+        let mut carry = c.to_digit(radix).expect("validated digit");
+        for digit in &mut decimal {
+            let n = *digit as u32 * radix + carry;
+            *digit = (n % 10) as u8;
+            carry = n / 10;
+        }
+        while carry > 0 {
+            decimal.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+    let mut out = String::with_capacity(decimal.len() + 1);
+    match decimal.is_empty() {
+        true => out.push('0'),
+        false if negative => out.push('-'),
+        false => {}
+    }
+    out.extend(decimal.iter().rev().map(|&digit| char::from(b'0' + digit)));
+    out
+}
+
+/// The form `<digits>e<exponent>` of a decimal literal, with neither leading
+/// nor trailing zeros in the digits, and `0e0` for zero. Text that is not a
+/// decimal literal, or whose exponent is beyond `i128`, is returned as is.
+pub(crate) fn canonical_decimal(s: &str) -> String {
+    if parse_number(s) != Some(Number::Decimal) {
+        return s.to_string();
+    }
+    let (negative, body) = split_sign(s);
+    let (mantissa, exponent) = body.split_once(['e', 'E']).unwrap_or((body, "0"));
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let fraction = without_separators(fraction);
+    let digits = format!("{}{}", without_separators(whole), fraction);
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return "0e0".to_string();
+    }
+    let exponent = without_separators(exponent)
+        .parse::<i128>()
+        .ok()
+        .and_then(|exponent| exponent.checked_sub(fraction.len() as i128))
+        .and_then(|exponent| exponent.checked_add((digits.len() - significant.len()) as i128));
+    match exponent {
+        Some(exponent) => format!("{}{}e{}", if negative { "-" } else { "" }, significant, exponent),
+        None => s.to_string(),
+    }
+}
@@ -0,0 +1,41 @@
+use crate::frame::DuplicateKeys;
+use crate::limits::ParseLimits;
+use crate::number::NumberOptions;
+
+/// Settings shared by every parser front end, which all take them through
+/// their `with_options`. The defaults accept any input a lenient reader
+/// would, without bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) limits: ParseLimits,
+    pub(crate) numbers: NumberOptions,
+    pub(crate) strict: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+    /// Sets how a key repeated within a map is handled.
+    pub fn with_duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// Sets what integers beyond `i128` and decimals are read as.
+    pub fn with_numbers(mut self, numbers: NumberOptions) -> Self {
+        self.numbers = numbers;
+        self
+    }
+    /// In strict mode, unknown escapes such as `\q` are errors instead of
+    /// standing for the escaped character, and so are barewords that start
+    /// like a number but are not one, such as `1e400` or `007`.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::{self, Write};
+
+use crate::expr::Expr;
+use crate::writer::{self, write_scalar};
+
+/// Width of `expr` in compact form, or `None` once it exceeds `budget`.
+fn compact_width(expr: &Expr, budget: usize) -> Option<usize> {
+    let width = match expr {
+        Expr::Array(items) => {
+            let mut width = 1 + items.len().max(1);
+            for item in items {
+                width += compact_width(item, budget.checked_sub(width)?)?;
+            }
+            width
+        }
+        Expr::Map(entries) => {
+            let mut width = 1 + (2 * entries.len()).max(1);
+            for (key, value) in entries {
+                width += string_width(key);
+                width += compact_width(value, budget.checked_sub(width)?)?;
+            }
+            width
+        }
+        Expr::String(s) if writer::is_block(s) => return None,
+        scalar => scalar_width(scalar),
+    };
+    (width <= budget).then_some(width)
+}
+
+fn scalar_width(expr: &Expr) -> usize {
+    let mut text = String::new();
+    write_scalar(&mut text, expr).expect("writing to a String");
+    text.chars().count()
+}
+
+fn string_width(s: &str) -> usize {
+    scalar_width(&Expr::String(s.to_string()))
+}
+
+/// Lays out an [`Expr`] over several lines, keeping arrays and maps inline
+/// while they fit within the maximum width.
+#[derive(Debug, Clone)]
+pub struct PrettyPrinter {
+    indent_width: usize,
+    tabs: bool,
+    max_width: usize,
+    trailing_newline: bool,
+    align_values: bool,
+}
+
+impl PrettyPrinter {
+    pub fn new() -> Self {
+        PrettyPrinter {
+            indent_width: 2,
+            tabs: false,
+            max_width: 80,
+            trailing_newline: true,
+            align_values: false,
+        }
+    }
+    /// Columns per level; also the width a tab counts for.
+    pub fn with_indent_width(mut self, width: usize) -> Self {
+        self.indent_width = width;
+        self
+    }
+    pub fn with_tabs(mut self, tabs: bool) -> Self {
+        self.tabs = tabs;
+        self
+    }
+    pub fn with_max_width(mut self, width: usize) -> Self {
+        self.max_width = width;
+        self
+    }
+    pub fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+    /// Pads the keys of multi-line maps so that their values line up.
+    pub fn with_aligned_values(mut self, align: bool) -> Self {
+        self.align_values = align;
+        self
+    }
+    pub(crate) fn indent_width(&self) -> usize {
+        self.indent_width
+    }
+    pub(crate) fn indent(&self, f: &mut impl Write, depth: usize) -> fmt::Result {
+        match self.tabs {
+            true => (0..depth).try_for_each(|_| f.write_char('\t')),
+            false => (0..depth * self.indent_width).try_for_each(|_| f.write_char(' ')),
+        }
+    }
+    /// Writes a multi-line string as a block string indented one level
+    /// deeper than `depth`.
+    fn write_block(&self, f: &mut impl Write, s: &str, depth: usize) -> fmt::Result {
+        f.write_str("\"\"\"")?;
+        for line in s.split('\n') {
+            f.write_char('\n')?;
+            if !line.is_empty() {
+                self.indent(f, depth + 1)?;
+                f.write_str(line)?;
+            }
+        }
+        f.write_char('\n')?;
+        self.indent(f, depth + 1)?;
+        f.write_str("\"\"\"")
+    }
+    /// Writes `expr` starting at `column` on a line indented `depth` levels.
+    pub(crate) fn write(&self, f: &mut impl Write, expr: &Expr, depth: usize, column: usize) -> fmt::Result {
+        if let Expr::String(s) = expr {
+            if writer::is_block(s) {
+                return self.write_block(f, s, depth);
+            }
+        }
+        let budget = self.max_width.saturating_sub(column);
+        if !matches!(expr, Expr::Array(_) | Expr::Map(_)) || compact_width(expr, budget).is_some() {
+            return writer::write_compact(f, expr);
+        }
+        let inner = (depth + 1) * self.indent_width;
+        match expr {
+            Expr::Array(items) => {
+                f.write_char('[')?;
+                for item in items {
+                    f.write_char('\n')?;
+                    self.indent(f, depth + 1)?;
+                    self.write(f, item, depth + 1, inner)?;
+                }
+                f.write_char('\n')?;
+                self.indent(f, depth)?;
+                f.write_char(']')
+            }
+            Expr::Map(entries) => {
+                let key_width = match self.align_values {
+                    true => entries.iter().map(|(key, _)| string_width(key)).max().unwrap_or(0),
+                    false => 0,
+                };
+                f.write_char('{')?;
+                for (key, value) in entries {
+                    f.write_char('\n')?;
+                    self.indent(f, depth + 1)?;
+                    writer::write_string(f, key)?;
+                    let width = string_width(key);
+                    (width..key_width).try_for_each(|_| f.write_char(' '))?;
+                    f.write_char(' ')?;
+                    self.write(f, value, depth + 1, inner + width.max(key_width) + 1)?;
+                }
+                f.write_char('\n')?;
+                self.indent(f, depth)?;
+                f.write_char('}')
+            }
+            _ => unreachable!("scalars are written compactly"),
+        }
+    }
+    pub fn print(&self, expr: &Expr) -> String {
+        let mut out = String::new();
+        self.write(&mut out, expr, 0, 0).expect("writing to a String");
+        if self.trailing_newline {
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        PrettyPrinter::new()
+    }
+}
@@ -1,118 +1,405 @@
-use std::{str::Chars, error::Error};
+use std::{borrow::Cow, str::Chars};
 
+use crate::error::{ErrorKind, Position, PsonError};
+use crate::event::{Event, Sink};
 use crate::expr::Expr;
-use crate::frame::{Frame, FrameKind};
+use crate::expr_ref::ExprRef;
+use crate::frame::{DuplicateKeys, FrameKind, TreeBuilder};
+use crate::lexer::{token_value, RawToken, TokenKind, Tokenizer};
+use crate::limits::Limit;
+use crate::map::Map;
+use crate::number::looks_numeric;
+use crate::options::ParseOptions;
+use crate::values::Values;
 
-pub struct PsonParser<'a> {
-    frame_stack: Vec<Frame>,
-    buffer: String,
-    it: Chars<'a>,
+/// A frame as far as the scanner is concerned: just enough to validate
+/// nesting and tell keys from values.
+#[derive(Debug)]
+struct OpenFrame<'a> {
+    kind: FrameKind,
+    opened: Position,
+    len: usize,
+    /// Set while recovering from a container used as a map key; its events
+    /// are dropped and it is reported as an empty key once closed.
+    muted: bool,
+    /// Keys seen so far with where they appeared, tracked only when
+    /// duplicates are an error.
+    keys: Map<Cow<'a, str>, Position>,
 }
 
-impl PsonParser<'_> {
-    pub fn new<'a>(text: Chars<'a>) -> PsonParser<'a> {
-        PsonParser {
-            frame_stack: vec![Frame::new(FrameKind::Array)],
-            buffer: String::new(),
-            it: text
+impl<'a> OpenFrame<'a> {
+    fn new(kind: FrameKind, opened: Position, muted: bool, keys: Map<Cow<'a, str>, Position>) -> Self {
+        OpenFrame {
+            kind,
+            opened,
+            len: 0,
+            muted,
+            keys,
         }
     }
-    pub fn with_buffer_capacity<'a>(text: Chars<'a>, capacity: usize) -> PsonParser<'a> {
-        PsonParser {
-            frame_stack: vec![Frame::new(FrameKind::Array)],
-            buffer: String::with_capacity(capacity),
-            it: text
+    fn expects_key(&self) -> bool {
+        self.kind == FrameKind::Map && self.len % 2 == 0
+    }
+}
+
+/// The structural half of every parser front end: consumes tokens from the
+/// [`Tokenizer`], validates nesting and hands the resulting [`Event`]s to
+/// its sink, which builds a tree directly or queues them for a pull parser.
+///
+/// Given the source text it is fed from, strings in the events borrow from it.
+pub(crate) struct Scanner<'a, S> {
+    tokenizer: Tokenizer,
+    source: Option<&'a str>,
+    frame_stack: Vec<OpenFrame<'a>>,
+    /// Emptied key maps of closed frames, reused by the next ones opened.
+    spare_keys: Vec<Map<Cow<'a, str>, Position>>,
+    pub(crate) sink: S,
+    pub(crate) options: ParseOptions,
+    nodes: usize,
+}
+
+impl<S> Scanner<'static, S> {
+    pub(crate) fn with_buffer_capacity(capacity: usize, sink: S) -> Self {
+        let mut tokenizer = Tokenizer::with_buffer_capacity(capacity);
+        tokenizer.trivia = false;
+        Scanner {
+            tokenizer,
+            source: None,
+            frame_stack: vec![OpenFrame::new(FrameKind::Array, Position::default(), false, Map::new())],
+            spare_keys: Vec::new(),
+            sink,
+            options: ParseOptions::new(),
+            nodes: 0,
+        }
+    }
+}
+
+impl<'a, S: Sink<'a>> Scanner<'a, S> {
+    /// The scanner must then be fed exactly the characters of `source`.
+    pub(crate) fn with_source(source: &'a str, sink: S) -> Self {
+        let mut tokenizer = Tokenizer::lazy();
+        tokenizer.trivia = false;
+        Scanner {
+            tokenizer,
+            source: Some(source),
+            frame_stack: vec![OpenFrame::new(FrameKind::Array, Position::default(), false, Map::new())],
+            spare_keys: Vec::new(),
+            sink,
+            options: ParseOptions::new(),
+            nodes: 0,
+        }
+    }
+    pub(crate) fn position(&self) -> Position {
+        self.tokenizer.position()
+    }
+    pub(crate) fn set_recover(&mut self, recover: bool) {
+        self.tokenizer.recover = recover;
+    }
+    pub(crate) fn set_options(&mut self, options: ParseOptions) {
+        self.options = options;
+        self.tokenizer.strict = options.strict;
+    }
+    pub(crate) fn take_errors(&mut self) -> Vec<PsonError> {
+        std::mem::take(&mut self.tokenizer.errors)
+    }
+    fn report(&mut self, error: PsonError) -> Result<(), PsonError> {
+        self.tokenizer.report(error)
+    }
+    fn top(&mut self) -> &mut OpenFrame<'a> {
+        self.frame_stack.last_mut().expect("root frame is never popped")
+    }
+    fn emit(&mut self, event: Event<'a>, at: Position) {
+        if !self.top().muted {
+            self.sink.push(event, at);
+        }
+    }
+    /// Limits are not subject to recovery: they exist to stop reading input.
+    fn check_limit(&self, limit: Limit, max: usize, value: usize, at: Position) -> Result<(), PsonError> {
+        match value > max {
+            true => Err(PsonError::new(ErrorKind::LimitExceeded { limit, max }, at)),
+            false => Ok(()),
         }
     }
-    pub(crate) fn process_buffer(&mut self) -> Result<(), Box<dyn Error>>{
-        if !self.buffer.is_empty() {
-            let top = self.frame_stack.last_mut().ok_or("invalid pson")?;
-            top.push(Expr::from(&self.buffer)?);
-            self.buffer.clear();
+    /// Counts one more item in the innermost frame, which must stay within limits.
+    fn count_item(&mut self, at: Position) -> Result<(), PsonError> {
+        let limits = self.options.limits;
+        self.nodes += 1;
+        self.check_limit(Limit::Nodes, limits.max_nodes, self.nodes, at)?;
+        let nested = self.frame_stack.len() > 1;
+        let top = self.top();
+        top.len += 1;
+        let len = match top.kind {
+            FrameKind::Array => top.len,
+            FrameKind::Map => top.len.div_ceil(2),
+        };
+        if nested {
+            self.check_limit(Limit::CollectionLength, limits.max_collection_len, len, at)?;
         }
         Ok(())
     }
-    pub(crate) fn read_hex_escape(&mut self) -> Result<(), Box<dyn Error>> {
-        let mut buf = String::with_capacity(2);
-        for _ in 0..2 {
-            if let Some(c) = self.it.next() {
-                buf.push(c);
-            } else {
-                Err("invalid pson")?;
+    fn check_string(&self, len: usize, at: Position) -> Result<(), PsonError> {
+        self.check_limit(Limit::StringBytes, self.options.limits.max_string_bytes, len, at)
+    }
+    fn push_event(&mut self, event: Event<'a>, start: Position) -> Result<(), PsonError> {
+        self.count_item(start)?;
+        self.emit(event, start);
+        Ok(())
+    }
+    fn push_key(&mut self, key: Cow<'a, str>, start: Position) -> Result<(), PsonError> {
+        if self.options.duplicate_keys == DuplicateKeys::Error && !self.top().muted {
+            match self.top().keys.get(&key) {
+                Some(&first) => {
+                    let error = PsonError::new(ErrorKind::DuplicateKey(key.to_string()), start).opened_at(first);
+                    self.report(error)?;
+                }
+                None => {
+                    self.top().keys.insert(key.clone(), start);
+                }
             }
         }
-        let n = u8::from_str_radix(&buf, 16).map_err(|_| "invalid pson")?;
-        self.buffer.push(n as char);
-        Ok(())
+        self.push_event(Event::Key(key), start)
     }
-    pub(crate) fn scan_quoted_string(&mut self) -> Result<(), Box<dyn Error>>{
-        self.process_buffer()?;
-        while let Some(c) = self.it.next() {
-            match c {
-                '"' => break,
-                '\\' => {
-                    if let Some(c) = self.it.next() {
-                        match c {
-                            'n' => self.buffer.push('\n'),
-                            't' => self.buffer.push('\t'),
-                            'r' => self.buffer.push('\r'),
-                            '"' => self.buffer.push('"'),
-                            '\\' => self.buffer.push('\\'),
-                            'x' => self.read_hex_escape()?,
-                             _  => self.buffer.push(c)
-                        }
-                    } else {
-                        Err("invalid pson")?;
-                    }
+    fn push_string(&mut self, value: Cow<'a, str>, start: Position) -> Result<(), PsonError> {
+        if self.top().expects_key() {
+            return self.push_key(value, start);
+        }
+        self.push_event(Event::Scalar(ExprRef::String(value)), start)
+    }
+    fn push_bareword(&mut self, text: Cow<'a, str>, start: Position) -> Result<(), PsonError> {
+        if !self.top().expects_key() {
+            let (value, error) = ExprRef::from_bareword_with(text, self.options.numbers);
+            if let Some(kind) = error {
+                self.report(PsonError::new(kind, start))?;
+            }
+            if let (ExprRef::String(word), true) = (&value, self.options.strict) {
+                if looks_numeric(word) {
+                    self.report(PsonError::new(ErrorKind::InvalidNumber(word.to_string()), start))?;
                 }
-                _ => self.buffer.push(c)
             }
+            return self.push_event(Event::Scalar(value), start);
+        }
+        if !matches!(ExprRef::from_bareword(Cow::Borrowed(&text)), ExprRef::String(_)) {
+            self.report(PsonError::new(ErrorKind::NonStringKey, start))?;
+        }
+        self.push_key(text, start)
+    }
+    fn open_frame(&mut self, kind: FrameKind, at: Position) -> Result<(), PsonError> {
+        let mut muted = self.top().muted;
+        if self.top().expects_key() {
+            self.report(PsonError::new(ErrorKind::NonStringKey, at))?;
+            muted = true;
+        }
+        self.count_item(at)?;
+        self.check_limit(Limit::Depth, self.options.limits.max_depth, self.frame_stack.len(), at)?;
+        if !muted {
+            let event = match kind {
+                FrameKind::Array => Event::StartArray,
+                FrameKind::Map => Event::StartMap,
+            };
+            self.sink.push(event, at);
         }
-        let top = self.frame_stack.last_mut().ok_or("invalid pson")?;
-        top.push(Expr::String(self.buffer.clone()));
-        self.buffer.clear();
+        let keys = self.spare_keys.pop().unwrap_or_default();
+        self.frame_stack.push(OpenFrame::new(kind, at, muted, keys));
         Ok(())
     }
-    pub(crate) fn close_frame(&mut self, brace: char) -> Result<(), Box<dyn Error>> {
-        self.process_buffer()?;
-        let mut frame = self.frame_stack.pop().ok_or("invalid pson")?;
-        let top = self.frame_stack.last_mut().ok_or("invalid pson")?;
-        match frame.kind {
-            FrameKind::Array =>
-                if brace != ']' {
-                    Err("invalid pson")?
-                } else {
-                    top.push(frame.to_array()?)
-                },
-            FrameKind::Map =>
-                if brace != '}' {
-                    Err("invalid pson")?
-                } else {
-                    top.push(frame.to_map()?)
-                },
-        };
+    fn pop_frame(&mut self, at: Position) -> Result<(), PsonError> {
+        let top = self.top();
+        let opened = top.opened;
+        if top.kind == FrameKind::Map && !top.expects_key() {
+            self.report(PsonError::new(ErrorKind::OddMapEntries, at).opened_at(opened))?;
+            self.push_event(Event::Scalar(ExprRef::Null()), at)?;
+        }
+        let mut frame = self.frame_stack.pop().expect("root frame is never popped");
+        frame.keys.clear();
+        self.spare_keys.push(frame.keys);
+        if !frame.muted {
+            let event = match frame.kind {
+                FrameKind::Array => Event::EndArray,
+                FrameKind::Map => Event::EndMap,
+            };
+            self.sink.push(event, at);
+        } else {
+            self.emit(Event::Key(Cow::Borrowed("")), opened);
+        }
         Ok(())
     }
-    pub fn parse(&mut self) -> Result<(), Box<dyn Error>> {
-        while let Some(c) = self.it.next() {
-            match c {
-                '[' => self.frame_stack.push(Frame::new(FrameKind::Array)),
-                '{' => self.frame_stack.push(Frame::new(FrameKind::Map)),
-                ']' | '}' => self.close_frame(c)?,
-                ' ' | '\t' | '\n' | '\r' => self.process_buffer()?,
-                '"' => self.scan_quoted_string()?,
-                _ => self.buffer.push(c)
+    fn close_frame(&mut self, brace: char, at: Position) -> Result<(), PsonError> {
+        if self.frame_stack.len() == 1 {
+            return self.report(PsonError::new(ErrorKind::UnexpectedClose(brace), at));
+        }
+        let frame = self.top();
+        let (expected, opened) = (frame.kind.closer(), frame.opened);
+        if brace != expected {
+            self.report(PsonError::new(ErrorKind::MismatchedBracket { expected, found: brace }, at)
+                .opened_at(opened))?;
+            // Resynchronize on the nearest enclosing frame the bracket does close,
+            // or drop the stray bracket if there is none.
+            let Some(depth) = self.frame_stack[1..].iter().rposition(|f| f.kind.closer() == brace) else {
+                return Ok(());
+            };
+            while self.frame_stack.len() > depth + 2 {
+                self.pop_frame(at)?;
             }
-        };
-        self.process_buffer()?;
+        }
+        self.pop_frame(at)
+    }
+    fn token(&mut self, token: RawToken) -> Result<(), PsonError> {
+        let start = token.start;
+        match token.kind {
+            TokenKind::OpenBracket => self.open_frame(FrameKind::Array, start),
+            TokenKind::OpenBrace => self.open_frame(FrameKind::Map, start),
+            TokenKind::Close => self.close_frame(token.lead, start),
+            TokenKind::Bareword => {
+                let text = self.value(token);
+                self.check_string(text.len(), start)?;
+                self.push_bareword(text, start)
+            }
+            TokenKind::QuotedString | TokenKind::RawString | TokenKind::BlockString => {
+                let value = self.value(token);
+                self.check_string(value.len(), start)?;
+                self.push_string(value, start)
+            }
+            TokenKind::Whitespace | TokenKind::Comment => Ok(()),
+        }
+    }
+    fn value(&self, token: RawToken) -> Cow<'a, str> {
+        match self.source {
+            Some(source) => token_value(source, token),
+            None => Cow::Owned(token.value),
+        }
+    }
+    fn process_tokens(&mut self) -> Result<(), PsonError> {
+        while let Some(token) = self.tokenizer.tokens.pop_front() {
+            self.token(token)?;
+        }
         Ok(())
     }
-    pub fn get(&mut self) -> Result<Expr, Box<dyn Error>> {
-        if self.frame_stack.len() != 1 {
-            Err("invalid pson")?;
+    /// Stops a string from growing past the limit before it is even complete.
+    fn check_pending(&self) -> Result<(), PsonError> {
+        let (len, start) = self.tokenizer.pending();
+        self.check_string(len, start)
+    }
+    /// Feeds a batch of tokens from the start of `text`, returning how many
+    /// bytes were consumed.
+    pub(crate) fn feed(&mut self, text: &str) -> Result<usize, PsonError> {
+        let fed = self.tokenizer.feed(text);
+        // Tokens completed before a tokenizer error come first.
+        self.process_tokens()?;
+        let fed = fed?;
+        self.check_pending()?;
+        Ok(fed)
+    }
+    pub(crate) fn push_str(&mut self, text: &str) -> Result<(), PsonError> {
+        let mut rest = text;
+        while !rest.is_empty() {
+            let fed = self.feed(rest)?;
+            rest = &rest[fed..];
+        }
+        Ok(())
+    }
+    /// Signals the end of input, flushing any pending bareword or string.
+    pub(crate) fn end(&mut self) -> Result<(), PsonError> {
+        self.tokenizer.end()?;
+        self.process_tokens()
+    }
+    /// Closes whatever frames are still open once the input is exhausted.
+    pub(crate) fn finish(&mut self) -> Result<(), PsonError> {
+        while self.frame_stack.len() > 1 {
+            let position = self.position();
+            let frame = self.top();
+            let error = PsonError::new(ErrorKind::UnclosedFrame(frame.kind.closer()), position)
+                .opened_at(frame.opened);
+            self.report(error)?;
+            self.pop_frame(position)?;
         }
-        let top = self.frame_stack.pop().ok_or("invalid pson")?;
-        let expr = top.to_array()?;
-        Ok(expr)
+        Ok(())
+    }
+}
+
+pub struct PsonParser<'a> {
+    scanner: Scanner<'a, TreeBuilder<'a, Expr>>,
+    it: Chars<'a>,
+}
+
+impl<'a> PsonParser<'a> {
+    pub fn new(text: Chars<'a>) -> PsonParser<'a> {
+        PsonParser {
+            scanner: Scanner::with_source(text.as_str(), TreeBuilder::new()),
+            it: text
+        }
+    }
+    #[deprecated(note = "the capacity is ignored: strings are sliced out of the input; use `PsonParser::new`")]
+    pub fn with_buffer_capacity(text: Chars<'a>, _capacity: usize) -> PsonParser<'a> {
+        PsonParser::new(text)
+    }
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.scanner.set_options(options);
+        self.scanner.sink.duplicate_keys = options.duplicate_keys;
+        self
+    }
+    pub fn position(&self) -> Position {
+        self.scanner.position()
+    }
+    /// Validates `bytes` as UTF-8 once up front, then parses them as text.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<PsonParser<'a>, PsonError> {
+        let text = std::str::from_utf8(bytes).map_err(|e| {
+            let mut position = Position::default();
+            position.advance_str(std::str::from_utf8(&bytes[..e.valid_up_to()]).expect("validated prefix"));
+            PsonError::new(ErrorKind::InvalidUtf8, position)
+        })?;
+        Ok(PsonParser::new(text.chars()))
+    }
+    pub fn parse(&mut self) -> Result<(), PsonError> {
+        let text = self.it.as_str();
+        self.it = "".chars();
+        self.scanner.push_str(text)?;
+        self.scanner.end()
+    }
+    pub fn get(&mut self) -> Result<Expr, PsonError> {
+        self.scanner.finish()?;
+        Ok(self.scanner.sink.get())
+    }
+    /// Parses the whole input as a sequence of top-level values, which is what
+    /// [`PsonParser::get`] wraps in the implicit root array.
+    pub fn parse_sequence(&mut self) -> Result<Vec<Expr>, PsonError> {
+        self.parse()?;
+        match self.get()? {
+            Expr::Array(values) => Ok(values),
+            _ => unreachable!("the root frame is an array"),
+        }
+    }
+    /// Parses an input holding exactly one top-level value.
+    pub fn parse_value(&mut self) -> Result<Expr, PsonError> {
+        let text = std::mem::replace(&mut self.it, "".chars());
+        let mut values = Values::new(text).with_options(self.scanner.options);
+        let value = match values.next_positioned() {
+            Some(value) => value?.0,
+            None => return Err(PsonError::new(ErrorKind::MissingValue, values.position())),
+        };
+        match values.next_positioned() {
+            None => Ok(value),
+            Some(Ok((_, position))) => Err(PsonError::new(ErrorKind::TrailingValue, position)),
+            Some(Err(error)) => Err(error),
+        }
+    }
+    /// Yields the top-level values of the input one by one as they close.
+    pub fn values(self) -> Values<'a> {
+        Values::new(self.it).with_options(self.scanner.options)
+    }
+    /// Parses the whole input, recovering from every error instead of stopping
+    /// at the first one. Returns a best-effort tree along with all problems found.
+    ///
+    /// Exceeding a [`crate::ParseLimits`] bound still stops the parse; the tree is
+    /// then an empty root array and the last error is the one that stopped it.
+    pub fn parse_recovering(&mut self) -> (Expr, Vec<PsonError>) {
+        self.scanner.set_recover(true);
+        let result = self.parse().and_then(|_| self.get());
+        self.scanner.set_recover(false);
+        let mut errors = self.scanner.take_errors();
+        let expr = result.unwrap_or_else(|error| {
+            errors.push(error);
+            Expr::Array(Vec::new())
+        });
+        (expr, errors)
     }
 }
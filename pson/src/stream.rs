@@ -0,0 +1,132 @@
+use std::io::{self, Read};
+
+use crate::error::{ErrorKind, Position, PsonError};
+use crate::expr::Expr;
+use crate::frame::TreeBuilder;
+use crate::options::ParseOptions;
+use crate::scanner::Scanner;
+
+fn utf8_width(lead: u8) -> usize {
+    match lead {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    }
+}
+
+/// Push-style parser fed with byte chunks as they arrive.
+///
+/// UTF-8 is decoded incrementally, so a chunk may end in the middle of a
+/// character.
+pub struct PushParser {
+    scanner: Scanner<'static, TreeBuilder<'static, Expr>>,
+    pending: Vec<u8>,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        PushParser::with_buffer_capacity(0)
+    }
+    pub fn with_buffer_capacity(capacity: usize) -> Self {
+        PushParser {
+            scanner: Scanner::with_buffer_capacity(capacity, TreeBuilder::new()),
+            pending: Vec::with_capacity(4),
+        }
+    }
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.scanner.set_options(options);
+        self.scanner.sink.duplicate_keys = options.duplicate_keys;
+        self
+    }
+    pub fn position(&self) -> Position {
+        self.scanner.position()
+    }
+    pub(crate) fn io_error(&self, error: io::Error) -> PsonError {
+        PsonError::new(ErrorKind::Io(error.kind()), self.scanner.position())
+    }
+    fn invalid_utf8(&self) -> PsonError {
+        PsonError::new(ErrorKind::InvalidUtf8, self.scanner.position())
+    }
+    pub fn feed_str(&mut self, text: &str) -> Result<(), PsonError> {
+        self.scanner.push_str(text)
+    }
+    pub fn feed(&mut self, mut bytes: &[u8]) -> Result<(), PsonError> {
+        if !self.pending.is_empty() {
+            let width = utf8_width(self.pending[0]);
+            let take = (width - self.pending.len()).min(bytes.len());
+            self.pending.extend_from_slice(&bytes[..take]);
+            bytes = &bytes[take..];
+            if self.pending.len() < width {
+                return Ok(());
+            }
+            let pending = std::mem::take(&mut self.pending);
+            let text = std::str::from_utf8(&pending).map_err(|_| self.invalid_utf8())?;
+            self.feed_str(text)?;
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => self.feed_str(text),
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                self.feed_str(std::str::from_utf8(valid).expect("validated prefix"))?;
+                match e.error_len() {
+                    None => {
+                        self.pending.extend_from_slice(rest);
+                        Ok(())
+                    }
+                    Some(_) => Err(self.invalid_utf8()),
+                }
+            }
+        }
+    }
+    pub fn finish(mut self) -> Result<Expr, PsonError> {
+        if !self.pending.is_empty() {
+            return Err(self.invalid_utf8());
+        }
+        self.scanner.end()?;
+        self.scanner.finish()?;
+        Ok(self.scanner.sink.get())
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+/// Parses a document pulled from any [`Read`] without loading it into memory first.
+pub struct PsonReader<R: Read> {
+    reader: R,
+    parser: PushParser,
+    chunk: Vec<u8>,
+}
+
+impl<R: Read> PsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        PsonReader::with_chunk_size(reader, 8192)
+    }
+    pub fn with_chunk_size(reader: R, size: usize) -> Self {
+        PsonReader {
+            reader,
+            parser: PushParser::new(),
+            chunk: vec![0; size.max(1)],
+        }
+    }
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.parser = self.parser.with_options(options);
+        self
+    }
+    pub fn parse(mut self) -> Result<Expr, PsonError> {
+        loop {
+            let n = match self.reader.read(&mut self.chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.parser.io_error(e)),
+            };
+            self.parser.feed(&self.chunk[..n])?;
+        }
+        self.parser.finish()
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use super::*;
 use pson_schema::pson_schemas;
 
@@ -13,9 +11,9 @@ fn general_test() {
         1.0
         "hello"
         [1 2 3]
-        (a 1 b 2 c 3)
+        {a 1 b 2 c 3}
         [1 [2 [3]]]
-        (a (b (c N)))
+        {a {b {c N}}}
     "#;
     let mut scanner = PsonParser::new(text.chars());
     scanner.parse().unwrap();
@@ -36,7 +34,7 @@ fn general_test() {
             ("a".to_string(), Expr::Integer(1)),
             ("b".to_string(), Expr::Integer(2)),
             ("c".to_string(), Expr::Integer(3)),
-        ].into_iter().collect::<HashMap<String, Expr>>()),
+        ].into_iter().collect::<Map<String, Expr>>()),
         Expr::Array(vec![
             Expr::Integer(1),
             Expr::Array(vec![
@@ -50,9 +48,9 @@ fn general_test() {
             ("a".to_string(), Expr::Map(vec![
                 ("b".to_string(), Expr::Map(vec![
                     ("c".to_string(), Expr::Null()),
-                ].into_iter().collect::<HashMap<String, Expr>>())),
-            ].into_iter().collect::<HashMap<String, Expr>>())),
-        ].into_iter().collect::<HashMap<String, Expr>>()),
+                ].into_iter().collect::<Map<String, Expr>>())),
+            ].into_iter().collect::<Map<String, Expr>>())),
+        ].into_iter().collect::<Map<String, Expr>>()),
     ]));
 }
 
@@ -77,19 +75,923 @@ fn long_array_test(){
 #[test]
 fn long_map_test(){
     let mut text = String::with_capacity(111111 * 4);
-    text.push('(');
+    text.push('{');
     text.push_str((0..100000).map(|i| format!("a{} 1 ", i)).collect::<String>().as_str());
-    text.push(')');
+    text.push('}');
     let mut scanner = PsonParser::new(text.chars());
     scanner.parse().unwrap();
     let expr = scanner.get().unwrap();
-    let mut map = HashMap::new();
+    let mut map = Map::new();
     for i in 0..100000 {
         map.insert(format!("a{}", i), Expr::Integer(1));
     }
     assert_eq!(expr, Expr::Array(vec![Expr::Map(map)]));
 }
 
+#[test]
+fn error_position_test(){
+    let text = "[1 2\n  {a 1 b]";
+    let mut scanner = PsonParser::new(text.chars());
+    let err = scanner.parse().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MismatchedBracket { expected: '}', found: ']' });
+    assert_eq!(err.position, Position { offset: 13, line: 2, column: 9 });
+    assert_eq!(err.opened_at, Some(Position { offset: 7, line: 2, column: 3 }));
+}
+
+#[test]
+fn error_kinds_test(){
+    let cases = [
+        ("\"abc", ErrorKind::UnterminatedString),
+        ("\"\\x1Z\"", ErrorKind::InvalidEscape("x1".to_string())),
+        ("1 ]", ErrorKind::UnexpectedClose(']')),
+        ("{a 1 b}", ErrorKind::OddMapEntries),
+        ("{[a] 1}", ErrorKind::NonStringKey),
+        ("{1 a}", ErrorKind::NonStringKey),
+    ];
+    for (text, kind) in cases {
+        let mut scanner = PsonParser::new(text.chars());
+        assert_eq!(scanner.parse().unwrap_err().kind, kind, "{}", text);
+    }
+    let mut scanner = PsonParser::new("[[1]".chars());
+    scanner.parse().unwrap();
+    let err = scanner.get().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnclosedFrame(']'));
+    assert_eq!(err.opened_at.map(|p| p.offset), Some(0));
+}
+
+#[test]
+fn recovering_test(){
+    let text = "[1 {a 1 b] } 2 {3 x} \"\\q\\x4 [T";
+    let mut scanner = PsonParser::new(text.chars());
+    let (expr, errors) = scanner.parse_recovering();
+    let kinds: Vec<ErrorKind> = errors.into_iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, vec![
+        ErrorKind::MismatchedBracket { expected: '}', found: ']' },
+        ErrorKind::OddMapEntries,
+        ErrorKind::UnexpectedClose('}'),
+        ErrorKind::NonStringKey,
+        ErrorKind::InvalidEscape("x4".to_string()),
+        ErrorKind::UnterminatedString,
+    ]);
+    assert_eq!(expr, Expr::Array(vec![
+        Expr::Array(vec![
+            Expr::Integer(1),
+            Expr::Map(vec![
+                ("a".to_string(), Expr::Integer(1)),
+                ("b".to_string(), Expr::Null()),
+            ].into_iter().collect::<Map<String, Expr>>()),
+        ]),
+        Expr::Integer(2),
+        Expr::Map(vec![
+            ("3".to_string(), Expr::String("x".to_string())),
+        ].into_iter().collect::<Map<String, Expr>>()),
+        Expr::String("q\\x4 [T".to_string()),
+    ]));
+
+    let (expr, errors) = PsonParser::new("{[a] 1}".chars()).parse_recovering();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(expr, Expr::Array(vec![Expr::Map(vec![
+        ("".to_string(), Expr::Integer(1)),
+    ].into_iter().collect::<Map<String, Expr>>())]));
+
+    let (expr, errors) = PsonParser::new("[[1".chars()).parse_recovering();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(expr, Expr::Array(vec![Expr::Array(vec![Expr::Array(vec![Expr::Integer(1)])])]));
+}
+
+#[test]
+fn push_parser_test(){
+    let text = "{name \"zażółć\" list [1 2.5 \"\\x41\"]}";
+    let expected = PsonParser::new(text.chars()).parse_recovering().0;
+    for size in 1..8 {
+        let mut parser = PushParser::new();
+        for chunk in text.as_bytes().chunks(size) {
+            parser.feed(chunk).unwrap();
+        }
+        assert_eq!(parser.finish().unwrap(), expected);
+    }
+    let mut parser = PushParser::new();
+    parser.feed(&[b'a', 0xC5]).unwrap();
+    assert_eq!(parser.finish().unwrap_err().kind, ErrorKind::InvalidUtf8);
+    let mut parser = PushParser::new();
+    assert_eq!(parser.feed(&[b'a', 0xFF, b'b']).unwrap_err().position.offset, 1);
+}
+
+#[test]
+fn reader_test(){
+    let text: String = (0..10000).map(|i| format!("{{k\u{e9}y{} [{} \"v\"]}} ", i, i)).collect();
+    let expr = PsonReader::with_chunk_size(text.as_bytes(), 7).parse().unwrap();
+    assert_eq!(expr.as_array().unwrap().len(), 10000);
+    assert_eq!(expr, PsonReader::new(text.as_bytes()).parse().unwrap());
+
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+    }
+    let err = PsonReader::new(Failing).parse().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Io(std::io::ErrorKind::BrokenPipe));
+}
+
+#[test]
+fn event_reader_test(){
+    let text = "1 {a [T \"x\"]} ";
+    let events: Vec<(Event, usize)> = EventReader::new(text.chars())
+        .map(|e| e.map(|(event, position)| (event, position.offset)))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(events, vec![
+        (Event::Scalar(ExprRef::Integer(1)), 0),
+        (Event::StartMap, 2),
+        (Event::Key("a".into()), 3),
+        (Event::StartArray, 5),
+        (Event::Scalar(ExprRef::Boolean(true)), 6),
+        (Event::Scalar(ExprRef::String("x".into())), 8),
+        (Event::EndArray, 11),
+        (Event::EndMap, 12),
+    ]);
+
+    let mut reader = EventReader::new("[1 }".chars());
+    assert_eq!(reader.next().unwrap().unwrap().0, Event::StartArray);
+    assert_eq!(reader.next().unwrap().unwrap().0, Event::Scalar(ExprRef::Integer(1)));
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MismatchedBracket { expected: ']', found: '}' });
+    assert!(reader.next().is_none());
+
+    let total: i128 = EventReader::new("[{n 1} {n 2} {n 3}]".chars())
+        .filter_map(|e| match e.unwrap().0 {
+            Event::Scalar(ExprRef::Integer(n)) => Some(n),
+            _ => None,
+        })
+        .sum();
+    assert_eq!(total, 6);
+}
+
+#[test]
+fn expr_ref_test(){
+    use std::borrow::Cow;
+
+    let text = "{plain \"quoted\" esc \"a\\\"b\"} word";
+    let expr = ExprRef::parse(text).unwrap();
+    let ExprRef::Array(items) = &expr else { panic!() };
+    let ExprRef::Map(map) = &items[0] else { panic!() };
+    assert!(map.keys().all(|k| matches!(k, Cow::Borrowed(_))));
+    assert!(matches!(&map["plain"], ExprRef::String(Cow::Borrowed("quoted"))));
+    assert!(matches!(&map["esc"], ExprRef::String(Cow::Owned(s)) if s == "a\"b"));
+    assert!(matches!(&items[1], ExprRef::String(Cow::Borrowed("word"))));
+
+    let mut scanner = PsonParser::new(text.chars());
+    scanner.parse().unwrap();
+    assert_eq!(expr.into_owned(), scanner.get().unwrap());
+    assert_eq!(ExprRef::parse("[1 }").unwrap_err().kind, ErrorKind::MismatchedBracket { expected: ']', found: '}' });
+}
+
+#[test]
+fn find_any_test(){
+    let bytes = b"abcdefghijklmnopqrstuvwxyz\x80\xff[0123456789";
+    for start in 0..bytes.len() {
+        let naive = bytes[start..].iter().position(|b| b"[\"".contains(b)).unwrap_or(bytes.len() - start);
+        assert_eq!(crate::memscan::find_any(&bytes[start..], b"[\""), naive);
+    }
+    assert_eq!(crate::memscan::find_any(b"", b"["), 0);
+}
+
+#[test]
+fn from_bytes_test(){
+    let text = "[\"multi\nline ż string\" long_bareword_with_no_delimiters] }";
+    let mut scanner = PsonParser::from_bytes(text.as_bytes()).unwrap();
+    let err = scanner.parse().unwrap_err();
+    assert_eq!(err.position, Position { offset: 58, line: 2, column: 50 });
+    let mut scanner = PsonParser::from_bytes(&text.as_bytes()[..57]).unwrap();
+    scanner.parse().unwrap();
+    assert_eq!(scanner.get().unwrap(), Expr::Array(vec![Expr::Array(vec![
+        Expr::String("multi\nline ż string".to_string()),
+        Expr::String("long_bareword_with_no_delimiters".to_string()),
+    ])]));
+    let err = PsonParser::from_bytes(b"ab\ncd\xC3(").err().unwrap();
+    assert_eq!(err.kind, ErrorKind::InvalidUtf8);
+    assert_eq!(err.position, Position { offset: 5, line: 2, column: 3 });
+}
+
+#[test]
+fn lexer_test(){
+    let text = "{k \"a\\tb\"}\n[x\"y\"]";
+    let tokens: Vec<(TokenKind, std::ops::Range<usize>, &str, String)> = Lexer::new(text)
+        .map(|t| (t.kind, t.span, t.text, t.value.into_owned()))
+        .collect();
+    assert_eq!(tokens, vec![
+        (TokenKind::OpenBrace, 0..1, "{", "{".to_string()),
+        (TokenKind::Bareword, 1..2, "k", "k".to_string()),
+        (TokenKind::Whitespace, 2..3, " ", " ".to_string()),
+        (TokenKind::QuotedString, 3..9, "\"a\\tb\"", "a\tb".to_string()),
+        (TokenKind::Close, 9..10, "}", "}".to_string()),
+        (TokenKind::Whitespace, 10..11, "\n", "\n".to_string()),
+        (TokenKind::OpenBracket, 11..12, "[", "[".to_string()),
+        (TokenKind::Bareword, 12..13, "x", "x".to_string()),
+        (TokenKind::QuotedString, 13..16, "\"y\"", "y".to_string()),
+        (TokenKind::Close, 16..17, "]", "]".to_string()),
+    ]);
+
+    let text = "a  \"ü\\x4\" \"open";
+    let mut lexer = Lexer::new(text);
+    let rebuilt: String = lexer.by_ref().map(|t| t.text).collect();
+    assert_eq!(rebuilt, text);
+    let kinds: Vec<ErrorKind> = lexer.errors().iter().map(|e| e.kind.clone()).collect();
+    assert_eq!(kinds, vec![ErrorKind::InvalidEscape("x4".to_string()), ErrorKind::UnterminatedString]);
+}
+
+#[test]
+fn report_test(){
+    let text = "{a 1\n b [1 2]\n";
+    let mut scanner = PsonParser::new(text.chars());
+    scanner.parse().unwrap();
+    let err = scanner.get().unwrap_err();
+    assert_eq!(err.report(text).to_string(), [
+        "error: unclosed frame: expected `}`",
+        " --> line 3, column 1",
+        "  |",
+        "1 | {a 1",
+        "  | - map opened here",
+        " ...",
+        "3 | ",
+        "  | ^",
+        "",
+    ].join("\n"));
+
+    let text = "[1\t{a]";
+    let err = PsonParser::new(text.chars()).parse().unwrap_err();
+    let report = err.report(text).with_color(true).to_string();
+    assert!(report.starts_with("\x1b[1;31merror\x1b[0m"));
+    assert!(report.contains("[1\t{a]"));
+    assert!(report.contains("\t\x1b[1;34m- map opened here"));
+}
+
+#[test]
+fn cst_test(){
+    let text = "x [ 1  2 ]\n{\n  name \"old\"\n  tags [a b]\n}\n";
+    let mut doc = Document::parse(text).unwrap();
+    assert_eq!(doc.to_string(), text);
+    assert_eq!(doc.get(&[Segment::Index(2), Segment::Key("name")]), Some(Expr::String("old".to_string())));
+
+    doc.set(&[Segment::Index(2), Segment::Key("name")], "\"new name\"").unwrap();
+    doc.insert(&[Segment::Index(2)], "a key", "[T F]").unwrap();
+    doc.remove(&[Segment::Index(2), Segment::Key("tags"), Segment::Index(0)]).unwrap();
+    doc.remove(&[Segment::Index(0)]).unwrap();
+    assert_eq!(doc.to_string(), "[ 1  2 ]\n{\n  name \"new name\"\n  tags [b]\n  \"a key\" [T F]\n}\n");
+
+    assert_eq!(doc.insert(&[Segment::Index(1)], "name", "1"), Err(EditError::DuplicateKey("name".to_string())));
+    assert_eq!(doc.insert(&[Segment::Index(0)], "k", "1"), Err(EditError::NotAMap));
+    assert_eq!(doc.set(&[Segment::Index(5)], "1"), Err(EditError::PathNotFound));
+    assert_eq!(doc.set(&[Segment::Index(0)], "1 2"), Err(EditError::InvalidValue(None)));
+    assert_eq!(doc.set(&[Segment::Index(0)], "// x\n5 // y"), Err(EditError::InvalidValue(None)));
+    assert_eq!(doc.insert(&[Segment::Index(1)], "k", "5 /* y */"), Err(EditError::InvalidValue(None)));
+    doc.set(&[Segment::Index(0)], " [1 /* one */] ").unwrap();
+    assert!(doc.to_string().starts_with("[1 /* one */]\n{"));
+    assert!(Document::parse("[").is_err());
+
+    // Comments are nodes of their own: edits neither copy nor drop them.
+    let mut doc = Document::parse("{\n  // the name\n  name \"x\"\n}").unwrap();
+    doc.insert(&[Segment::Index(0)], "age", "3").unwrap();
+    assert_eq!(doc.to_string(), "{\n  // the name\n  name \"x\"\n  age 3\n}");
+    assert_eq!(doc.get(&[Segment::Index(0), Segment::Key("age")]), Some(Expr::Integer(3)));
+    let mut doc = Document::parse("[a // about a\n b]").unwrap();
+    doc.remove(&[Segment::Index(0), Segment::Index(1)]).unwrap();
+    assert_eq!(doc.to_string(), "[a // about a\n ]");
+    let mut doc = Document::parse("{a /* one */ 1 // first\n}").unwrap();
+    doc.insert(&[Segment::Index(0)], "b", "2").unwrap();
+    assert_eq!(doc.to_string(), "{a /* one */ 1 // first\n b 2\n}");
+    assert_eq!(doc.to_expr(), PsonParser::new("[{a 1 b 2}]".chars()).parse_value().unwrap());
+    for (text, inserted) in [
+        ("{a 1 // one\n}", "{a 1 // one\n b 2\n}"),
+        ("{\n  x 0  a 1 // one\n}", "{\n  x 0  a 1 // one\n  b 2\n}"),
+    ] {
+        let mut doc = Document::parse(text).unwrap();
+        doc.insert(&[Segment::Index(0)], "b", "2").unwrap();
+        assert_eq!(doc.to_string(), inserted);
+    }
+    for (text, removed) in [
+        ("{a /* why */ 1 b 2}", "{/* why */ b 2}"),
+        ("{x 0 a /* why */ 1}", "{x 0 /* why */}"),
+        ("{x 0 a // why\n 1}", "{x 0 // why\n }"),
+    ] {
+        let mut doc = Document::parse(text).unwrap();
+        doc.remove(&[Segment::Index(0), Segment::Key("a")]).unwrap();
+        assert_eq!(doc.to_string(), removed);
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_reader_test(){
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// Yields `Pending` before every chunk to exercise resumption.
+    struct Trickle<'a> { bytes: &'a [u8], ready: bool }
+    impl futures_io::AsyncRead for Trickle<'_> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = buf.len().min(3).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+    struct Chunks(std::vec::IntoIter<std::io::Result<&'static [u8]>>);
+    impl futures_core::Stream for Chunks {
+        type Item = std::io::Result<&'static [u8]>;
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    let text = "{k\u{e9}y [1 \"v\"]} T";
+    let mut parser = PsonParser::new(text.chars());
+    parser.parse().unwrap();
+    let expected = parser.get().unwrap();
+    let reader = AsyncPsonReader::with_chunk_size(Trickle { bytes: text.as_bytes(), ready: false }, 2);
+    assert_eq!(block_on(reader.parse()).unwrap(), expected);
+
+    let chunks: Vec<std::io::Result<&'static [u8]>> = vec![Ok(b"[1 "), Ok(b"2]"), Err(std::io::ErrorKind::ConnectionReset.into())];
+    let err = block_on(parse_stream(Chunks(chunks.into_iter()))).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Io(std::io::ErrorKind::ConnectionReset));
+    assert_eq!(err.position.offset, 5);
+
+    let chunks: Vec<std::io::Result<&'static [u8]>> = vec![Ok(b"[[1 "), Ok(b"2]]")];
+    let options = ParseOptions::new().with_limits(ParseLimits::new().with_max_depth(1));
+    let err = block_on(parse_stream_with_options(Chunks(chunks.into_iter()), options)).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::LimitExceeded { limit: Limit::Depth, max: 1 });
+}
+
+#[test]
+fn document_modes_test(){
+    let value = PsonParser::new(" {a [1 2]} ".chars()).parse_value().unwrap();
+    assert_eq!(value.as_map().unwrap().len(), 1);
+
+    let err = PsonParser::new("{a 1}\n  T".chars()).parse_value().unwrap_err();
+    assert_eq!((err.kind, err.position.line, err.position.column), (ErrorKind::TrailingValue, 2, 3));
+    let err = PsonParser::new("  ".chars()).parse_value().unwrap_err();
+    assert_eq!((err.kind, err.position.offset), (ErrorKind::MissingValue, 2));
+    let err = PsonParser::new("[1".chars()).parse_value().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnclosedFrame(']'));
+
+    let sequence = PsonParser::new("1 [2] {k v}".chars()).parse_sequence().unwrap();
+    assert_eq!(sequence.len(), 3);
+
+    let mut values = PsonParser::new("1 [2 [3]] x ]".chars()).values();
+    assert_eq!(values.next(), Some(Ok(Expr::Integer(1))));
+    assert_eq!(values.next(), Some(Ok(Expr::Array(vec![Expr::Integer(2), Expr::Array(vec![Expr::Integer(3)])]))));
+    assert_eq!(values.next(), Some(Ok(Expr::String("x".to_string()))));
+    assert_eq!(values.next().unwrap().unwrap_err().kind, ErrorKind::UnexpectedClose(']'));
+    assert_eq!(values.next(), None);
+}
+
+#[test]
+fn lines_test(){
+    let input = b"{id 1}\n\n[1 2\r\nT\n\"bad\\q\" \xff\n{id 2} x\n";
+    let mut reader = PsonLinesReader::new(&input[..]);
+    assert_eq!(reader.next(), Some(Ok(PsonParser::new("{id 1}".chars()).parse_value().unwrap())));
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!((err.kind, err.position.line, err.position.offset), (ErrorKind::UnclosedFrame(']'), 3, 12));
+    assert_eq!(err.opened_at.map(|p| p.offset), Some(8));
+    assert_eq!(reader.next(), Some(Ok(Expr::Boolean(true))));
+    assert_eq!(reader.line_number(), 4);
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!((err.kind, err.position.line), (ErrorKind::InvalidUtf8, 5));
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!((err.kind, err.position.line, err.position.column), (ErrorKind::TrailingValue, 6, 8));
+    assert_eq!(reader.next(), None);
+
+    // An over-long line is skipped, and reading goes on with the next one.
+    let input = format!("[1]\n[{}]\n{{a 1 a 2}}\n", "1 ".repeat(100));
+    let options = ParseOptions::new().with_duplicate_keys(DuplicateKeys::LastWins);
+    let mut reader = PsonLinesReader::new(std::io::BufReader::with_capacity(4, input.as_bytes()))
+        .with_options(options)
+        .with_max_line_bytes(16);
+    assert_eq!(reader.next(), Some(Ok(Expr::Array(vec![Expr::Integer(1)]))));
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::LimitExceeded { limit: Limit::LineBytes, max: 16 });
+    assert_eq!((err.position.line, err.position.offset), (2, 4));
+    let expected = PsonParser::new("{a 2}".chars()).parse_value().unwrap();
+    assert_eq!(reader.next(), Some(Ok(expected)));
+    assert_eq!(reader.line_number(), 3);
+    assert_eq!(reader.next(), None);
+
+    // Lines of whitespace and comments hold no record, but a form feed is
+    // not PSON whitespace.
+    let mut reader = PsonLinesReader::new(&b"// header\n \t/* note */\r\n1 // one\n\x0c\n"[..]);
+    assert_eq!(reader.next(), Some(Ok(Expr::Integer(1))));
+    assert_eq!(reader.line_number(), 3);
+    assert_eq!(reader.next(), Some(Ok(Expr::String("\x0c".to_string()))));
+    assert_eq!(reader.next(), None);
+
+    let mut writer = PsonLinesWriter::new(Vec::new());
+    writer.write(&Expr::Array(vec![Expr::Integer(1), Expr::Null()])).unwrap();
+    writer.write(&Expr::Boolean(false)).unwrap();
+    writer.write(&Expr::String("two\nlines".to_string())).unwrap();
+    assert_eq!(writer.into_inner(), b"[1 N]\nF\n\"two\\nlines\"\n");
+}
+
+#[test]
+fn serializer_round_trip_test(){
+    let strings = ["hello world", "N", "T", "42", "-1.5", "a]b", "{", "q\"uote", "back\\slash", "", "line\nbreak\r\t", "\u{1}\u{7f}", "\u{e9}t\u{e9}", "plain"];
+    let mut items: Vec<Expr> = strings.iter().map(|s| Expr::String(s.to_string())).collect();
+    items.extend([1.0, -0.5, 1e20, 1e-7, f64::MAX, f64::INFINITY].map(Expr::Float));
+    items.extend([Expr::Integer(i128::MIN), Expr::Null(), Expr::Boolean(false)]);
+    let map: Map<String, Expr> = strings.iter().map(|s| (s.to_string(), Expr::Array(items.clone()))).collect();
+    let expr = Expr::Array(vec![Expr::Array(items), Expr::Map(map), Expr::Array(vec![])]);
+
+    let text = expr.to_string();
+    let mut parser = PsonParser::new(text.chars());
+    assert_eq!(parser.parse_value().unwrap(), expr);
+    assert_eq!(Expr::Float(1.0).to_string(), "1.0");
+    assert_eq!(Expr::String("hello world".to_string()).to_string(), "\"hello world\"");
+    assert_eq!(Expr::String("\u{1}".to_string()).to_string(), "\"\\x01\"");
+    let map = Expr::Map(Map::from_iter([("k".to_string(), Expr::String("v".to_string()))]));
+    assert_eq!(map.to_string(), "{k v}");
+}
+
+#[test]
+fn pretty_printer_test(){
+    let text = "{name margherita price 6.5 sizes [{name S cm 24} {name M cm 32}] tags [] \"long key\" {a 1}}";
+    let expr = PsonParser::new(text.chars()).parse_value().unwrap();
+    assert_eq!(expr.to_pretty_string(), concat!(
+        "{\n",
+        "  name margherita\n",
+        "  price 6.5\n",
+        "  sizes [{name S cm 24} {name M cm 32}]\n",
+        "  tags []\n",
+        "  \"long key\" {a 1}\n",
+        "}\n",
+    ));
+    let printer = PrettyPrinter::new()
+        .with_tabs(true)
+        .with_indent_width(4)
+        .with_max_width(24)
+        .with_trailing_newline(false)
+        .with_aligned_values(true);
+    assert_eq!(printer.print(&expr), concat!(
+        "{\n",
+        "\tname       margherita\n",
+        "\tprice      6.5\n",
+        "\tsizes      [\n",
+        "\t\t{name S cm 24}\n",
+        "\t\t{name M cm 32}\n",
+        "\t]\n",
+        "\ttags       []\n",
+        "\t\"long key\" {a 1}\n",
+        "}",
+    ));
+    assert_eq!(PrettyPrinter::new().print(&Expr::Integer(1)), "1\n");
+    assert_eq!(PsonParser::new(printer.print(&expr).chars()).parse_value().unwrap(), expr);
+}
+
+#[test]
+fn pson_writer_test(){
+    fn write_rows<W: std::io::Write>(mut writer: PsonWriter<W>) -> W {
+        for id in 0..2 {
+            writer.begin_map().unwrap();
+            writer.key("id").unwrap();
+            writer.value(&Expr::Integer(id)).unwrap();
+            writer.key("tags").unwrap();
+            writer.begin_array().unwrap();
+            writer.value(&Expr::String("a b".to_string())).unwrap();
+            writer.value(&Expr::Array(vec![Expr::Float(1.0)])).unwrap();
+            writer.end().unwrap();
+            writer.key("empty").unwrap();
+            writer.begin_map().unwrap();
+            writer.end().unwrap();
+            writer.end().unwrap();
+        }
+        writer.finish().unwrap()
+    }
+    let compact = write_rows(PsonWriter::new(Vec::new()));
+    assert_eq!(String::from_utf8(compact).unwrap(), "{id 0 tags [\"a b\" [1.0]] empty {}}\n{id 1 tags [\"a b\" [1.0]] empty {}}\n");
+    let pretty = String::from_utf8(write_rows(PsonWriter::pretty(Vec::new(), PrettyPrinter::new()))).unwrap();
+    assert!(pretty.starts_with("{\n  id 0\n  tags [\n    \"a b\"\n    [1.0]\n  ]\n  empty {}\n}\n{\n  id 1"));
+    assert_eq!(PsonParser::new(pretty.chars()).parse_sequence().unwrap().len(), 2);
+
+    let mut writer = PsonWriter::new(Vec::new());
+    assert!(matches!(writer.key("k"), Err(WriteError::UnexpectedKey)));
+    assert!(matches!(writer.end(), Err(WriteError::NothingToEnd)));
+    writer.begin_map().unwrap();
+    assert!(matches!(writer.value(&Expr::Null()), Err(WriteError::ExpectedKey)));
+    writer.key("k").unwrap();
+    assert!(matches!(writer.key("k2"), Err(WriteError::UnexpectedKey)));
+    assert!(matches!(writer.end(), Err(WriteError::MissingValue)));
+    writer.begin_array().unwrap();
+    assert!(matches!(writer.finish(), Err(WriteError::Unclosed(2))));
+}
+
+#[test]
+fn canonical_digest_test(){
+    let hex = |digest: [u8; 32]| digest.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    // The digest is SHA-256 of the canonical text, here the bare words themselves.
+    assert_eq!(hex(Expr::String("abc".to_string()).digest()), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(hex(Expr::String("a".repeat(1000)).digest()), "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3");
+
+    let a = PsonParser::new("{b [1 2.5] a {y \"s p\" x -0.0} c 3.0}".chars()).parse_value().unwrap();
+    let b = PsonParser::new("{c 3 a {x 0 y \"s p\"} b [1.0 2.5]}".chars()).parse_value().unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a.to_canonical_string(), "{a {x 0 y \"s p\"} b [1 2.5] c 3}");
+    assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+    assert_eq!(a.digest(), b.digest());
+    assert_ne!(a.digest(), Expr::Null().digest());
+
+    use std::hash::BuildHasher;
+    let state = std::collections::hash_map::RandomState::new();
+    assert_eq!(state.hash_one(&a), state.hash_one(&b));
+    assert_eq!(state.hash_one(Expr::Float(2.0)), state.hash_one(Expr::Integer(2)));
+
+    // Integers a float can only approximate are not equal to it.
+    assert_ne!(Expr::Integer(9007199254740993), Expr::Float(9007199254740992.0));
+    assert_ne!(Expr::Float(9007199254740992.0), Expr::Integer(9007199254740993));
+    assert_eq!(Expr::Integer(9007199254740992), Expr::Float(9007199254740992.0));
+    assert_ne!(Expr::Integer(i128::MAX), Expr::Float(2f64.powi(127)));
+    assert_eq!(Expr::Integer(i128::MIN), Expr::Float(-(2f64.powi(127))));
+    assert_ne!(Expr::Integer(i128::MAX).to_canonical_string(), Expr::Float(2f64.powi(127)).to_canonical_string());
+}
+
+#[test]
+fn ordered_map_test(){
+    let expr = PsonParser::new("{z 1 a 2 m {y 1 b 2} a 3}".chars())
+        .with_options(ParseOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins))
+        .parse_value()
+        .unwrap();
+    let map = expr.as_map().unwrap();
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["z", "a", "m"]);
+    assert_eq!(map["a"], Expr::Integer(2));
+    assert_eq!(expr.to_string(), "{z 1 a 2 m {y 1 b 2}}");
+    assert_eq!(expr, PsonParser::new("{m {b 2 y 1} a 2 z 1}".chars()).parse_value().unwrap());
+
+    let mut map: Map<String, Expr> = Map::new();
+    map.insert("b".to_string(), Expr::Null());
+    map.insert("a".to_string(), Expr::Null());
+    map.insert("c".to_string(), Expr::Null());
+    assert_eq!(map.insert("b".to_string(), Expr::Boolean(true)), Some(Expr::Null()));
+    assert_eq!(map.remove("a"), Some(Expr::Null()));
+    assert_eq!(map.iter().map(|(k, _)| k.as_str()).collect::<String>(), "bc");
+    assert_eq!(map.get("c"), Some(&Expr::Null()));
+
+    let borrowed = ExprRef::parse("{q 1 p 2}").unwrap();
+    let ExprRef::Array(items) = borrowed else { panic!() };
+    let ExprRef::Map(map) = &items[0] else { panic!() };
+    assert_eq!(map.keys().map(|k| k.as_ref()).collect::<String>(), "qp");
+}
+
+#[test]
+fn duplicate_keys_test(){
+    let text = "{a 1 b 2\n \"a\" 3 a 4}";
+    let parse = |policy| PsonParser::new(text.chars()).with_options(ParseOptions::new().with_duplicate_keys(policy)).parse_value();
+
+    let error = PsonParser::new(text.chars()).parse_value().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::DuplicateKey("a".to_string()));
+    assert_eq!((error.position.line, error.position.column), (2, 2));
+    assert_eq!(error.opened_at.map(|p| p.offset), Some(1));
+    assert!(error.report(text).to_string().contains("first defined here"));
+
+    assert_eq!(parse(DuplicateKeys::FirstWins).unwrap().to_string(), "{a 1 b 2}");
+    assert_eq!(parse(DuplicateKeys::LastWins).unwrap().to_string(), "{a 4 b 2}");
+    assert_eq!(parse(DuplicateKeys::Collect).unwrap().to_string(), "{a [1 3 4] b 2}");
+
+    // Keys are per map, and nested maps are checked too.
+    assert!(PsonParser::new("{a {a 1} b {a 2}}".chars()).parse_value().is_ok());
+    let mut parser = PushParser::new();
+    parser.feed_str("[{x {k 1 k 2}}]").unwrap_err();
+
+    let mut parser = PsonParser::new(text.chars());
+    let (expr, errors) = parser.parse_recovering();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(expr.to_string(), "[{a 1 b 2}]");
+}
+
+#[test]
+fn parse_limits_test(){
+    fn limited(text: &str, limits: ParseLimits) -> PsonParser<'_> {
+        PsonParser::new(text.chars()).with_options(ParseOptions::new().with_limits(limits))
+    }
+    let parse = |text: &str, limits| limited(text, limits).parse_sequence();
+    let exceeded = |limit, max| ErrorKind::LimitExceeded { limit, max };
+
+    let depth = ParseLimits::new().with_max_depth(2);
+    assert!(parse("[[1]] {a [2]}", depth).is_ok());
+    let error = parse("[{a [3]}]", depth).unwrap_err();
+    assert_eq!(error.kind, exceeded(Limit::Depth, 2));
+    assert_eq!(error.position.offset, 4);
+
+    let strings = ParseLimits::new().with_max_string_bytes(3);
+    assert!(parse("abc \"d\\ne\" {key 1}", strings).is_ok());
+    assert_eq!(parse("[abcd]", strings).unwrap_err().kind, exceeded(Limit::StringBytes, 3));
+    assert_eq!(parse("{keys 1}", strings).unwrap_err().kind, exceeded(Limit::StringBytes, 3));
+
+    let collections = ParseLimits::new().with_max_collection_len(2);
+    assert!(parse("1 2 3 [1 2] {a 1 b 2}", collections).is_ok());
+    assert_eq!(parse("[1 2 3]", collections).unwrap_err().position.offset, 5);
+    assert_eq!(parse("{a 1 b 2 c 3}", collections).unwrap_err().kind, exceeded(Limit::CollectionLength, 2));
+
+    let nodes = ParseLimits::new().with_max_nodes(4);
+    assert!(parse("{a [1]}", nodes).is_ok());
+    assert_eq!(parse("{a [1 2]}", nodes).unwrap_err().kind, exceeded(Limit::Nodes, 4));
+    assert_eq!(limited("1 2 3 4 5", nodes).values().count(), 5);
+    assert!(limited("1 2 3 4 5", nodes).values().last().unwrap().is_err());
+
+    // A streamed string is cut off while it grows, not once it is complete.
+    let options = ParseOptions::new().with_limits(ParseLimits::new().with_max_string_bytes(8));
+    let mut parser = PushParser::new().with_options(options);
+    parser.feed_str("\"0123").unwrap();
+    let error = parser.feed_str("45678").unwrap_err();
+    assert_eq!((error.kind, error.position.offset), (exceeded(Limit::StringBytes, 8), 0));
+
+    let mut parser = limited("] [[[1]]]", depth);
+    let (expr, errors) = parser.parse_recovering();
+    assert_eq!(expr, Expr::Array(Vec::new()));
+    assert_eq!(errors.iter().map(|e| &e.kind).collect::<Vec<_>>(), [&ErrorKind::UnexpectedClose(']'), &exceeded(Limit::Depth, 2)]);
+}
+
+#[test]
+fn comments_test(){
+    let text = "// header\n{name pson /* the /* nested */ name */ path /usr/bin\n url \"http://x/*y*/\" n 1 // tail\n}\n/";
+    let expected = "[{name pson path /usr/bin url http://x/*y*/ n 1} /]";
+    assert_eq!(PsonParser::new(text.chars()).parse_sequence().map(Expr::Array).unwrap().to_string(), expected);
+    for chunk in [1, 2, 7] {
+        let mut parser = PushParser::new();
+        for piece in text.as_bytes().chunks(chunk) {
+            parser.feed(piece).unwrap();
+        }
+        assert_eq!(parser.finish().unwrap().to_string(), expected);
+    }
+    assert_eq!(ExprRef::parse(text).unwrap().into_owned().to_string(), expected);
+
+    let comments: Vec<_> = Lexer::new(text)
+        .filter(|token| token.kind == TokenKind::Comment)
+        .map(|token| token.text)
+        .collect();
+    assert_eq!(comments, ["// header", "/* the /* nested */ name */", "// tail"]);
+    // A comment only starts where a token may; inside a bareword it is text.
+    assert_eq!(PsonParser::new("a//b".chars()).parse_value().unwrap(), Expr::String("a//b".to_string()));
+
+    let error = PsonParser::new("[1 /* a /* b */ 2]".chars()).parse_sequence().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::UnterminatedComment);
+    assert_eq!(error.opened_at.map(|p| p.offset), Some(3));
+
+    assert_eq!(Expr::String("//x".to_string()).to_string(), "\"//x\"");
+    assert_eq!(Expr::String("/*x".to_string()).to_string(), "\"/*x\"");
+    assert_eq!(Expr::String("/x".to_string()).to_string(), "/x");
+
+    let mut doc = Document::parse("{a 1 // one\n b 2 /* two */}").unwrap();
+    doc.set(&[Segment::Index(0), Segment::Key("b")], "3").unwrap();
+    assert_eq!(doc.to_string(), "{a 1 // one\n b 3 /* two */}");
+}
+
+#[test]
+fn unicode_escapes_test(){
+    let parse = |text: &str| PsonParser::new(text.chars()).parse_value();
+    let string = |s: &str| Expr::String(s.to_string());
+    assert_eq!(parse(r#""caf\u{e9} \u{1F600}\u{0041}\x41""#), Ok(string("café 😀AA")));
+    assert_eq!(PushParser::new().feed_str(r#"["\u{10FFFF}"]"#).map(|_| ()), Ok(()));
+
+    let cases = [
+        (r#""\xC3\xA9""#, "xC3"),
+        (r#""\u{D800}""#, "u{D800}"),
+        (r#""\u{110000}""#, "u{110000}"),
+        (r#""\u{}""#, "u{}"),
+        (r#""\u{1234567}""#, "u{123456"),
+        (r#""\u{12 }""#, "u{12"),
+        (r#""\u41""#, "u"),
+        (r#""\u{41"#, "u{41"),
+    ];
+    for (text, escape) in cases {
+        assert_eq!(parse(text).unwrap_err().kind, ErrorKind::InvalidEscape(escape.to_string()), "{}", text);
+    }
+    let (expr, errors) = PsonParser::new(r#""a\u{D800}b\u{62}""#.chars()).parse_recovering();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(expr, Expr::Array(vec![string("a\\u{D800}bb")]));
+
+    // Unknown escapes stand for the escaped character unless parsing strictly.
+    assert_eq!(parse(r#""\q""#), Ok(string("q")));
+    let strict = |text: &str| PsonParser::new(text.chars()).with_options(ParseOptions::new().with_strict(true)).parse_value();
+    let error = strict(r#""ab\q""#).unwrap_err();
+    assert_eq!((error.kind, error.position.offset), (ErrorKind::InvalidEscape("q".to_string()), 3));
+    assert!(strict(r#""\n\u{41}""#).is_ok());
+
+    let text = string("\u{1}\u{85}\u{e9}😀").to_string();
+    assert_eq!(text, r#""\x01\u{85}é😀""#);
+    assert_eq!(parse(&text), Ok(string("\u{1}\u{85}\u{e9}😀")));
+}
+
+#[test]
+fn raw_and_block_strings_test(){
+    let text = "[r\"C:\\dir\\\" r##\"say \"#hi\"#\"## r# r \"\" \"\"\"\n    SELECT *\n      FROM t\n    \"\"\" \"\"\"x \"y\" z\"\"\"]";
+    let expected = Expr::Array(vec![
+        Expr::String(r"C:\dir\".to_string()),
+        Expr::String(r##"say "#hi"#"##.to_string()),
+        Expr::String("r#".to_string()),
+        Expr::String("r".to_string()),
+        Expr::String("".to_string()),
+        Expr::String("SELECT *\n  FROM t".to_string()),
+        Expr::String("x \"y\" z".to_string()),
+    ]);
+    assert_eq!(PsonParser::new(text.chars()).parse_value(), Ok(expected.clone()));
+    let mut parser = PushParser::new();
+    for piece in text.as_bytes().chunks(3) {
+        parser.feed(piece).unwrap();
+    }
+    assert_eq!(parser.finish().unwrap(), Expr::Array(vec![expected.clone()]));
+    let kinds: Vec<_> = Lexer::new(text).map(|token| token.kind).filter(|&kind| kind != TokenKind::Whitespace).collect();
+    assert_eq!(kinds[1..3], [TokenKind::RawString, TokenKind::RawString]);
+    assert_eq!(kinds[6..8], [TokenKind::BlockString, TokenKind::BlockString]);
+
+    // The closing delimiter's indentation counts; blank lines keep their place.
+    let block = "\"\"\"\n\t  a\n\n\t    b\n\t  \"\"\"";
+    assert_eq!(PsonParser::new(block.chars()).parse_value(), Ok(Expr::String("a\n\n  b".to_string())));
+    let error = PsonParser::new("r#\"open\"".chars()).parse_value().unwrap_err();
+    assert_eq!((error.kind, error.opened_at.map(|p| p.offset)), (ErrorKind::UnterminatedString, Some(0)));
+
+    // Three quotes open a block string even where they once meant two empty strings.
+    for quotes in ["\"\"\"\"", "\"\"\"\"\""] {
+        let error = PsonParser::new(quotes.chars()).parse_value().unwrap_err();
+        assert_eq!((error.kind, error.opened_at.map(|p| p.offset)), (ErrorKind::UnterminatedString, Some(0)), "{}", quotes);
+    }
+    let values = |text: &str| PsonParser::new(text.chars()).parse_recovering().0;
+    assert_eq!(values("\"\" \"\""), Expr::Array(vec![Expr::String(String::new()), Expr::String(String::new())]));
+    assert_eq!(values("\"\"\"\"\"\""), Expr::Array(vec![Expr::String(String::new())]));
+
+    let string = |s: &str| Expr::String(s.to_string());
+    assert_eq!(string(r"C:\My Files").to_string(), r#"r"C:\My Files""#);
+    assert_eq!(string(r#"say "hi" \o/"#).to_string(), r##"r#"say "hi" \o/"#"##);
+    assert_eq!(string(r#"say "hi""#).to_string(), r#""say \"hi\"""#);
+    assert_eq!(string("tab\t\\").to_string(), r#""tab\t\\""#);
+
+    let expr = Expr::Map(Map::from_iter([
+        ("query".to_string(), string("SELECT *\n  FROM t\n")),
+        ("path".to_string(), string(r"C:\My Files")),
+        ("note".to_string(), string("\nx")),
+    ]));
+    let pretty = expr.to_pretty_string();
+    assert_eq!(pretty, "{\n  query \"\"\"\n    SELECT *\n      FROM t\n\n    \"\"\"\n  path r\"C:\\My Files\"\n  note \"\"\"\n\n    x\n    \"\"\"\n}\n");
+    assert_eq!(PsonParser::new(pretty.chars()).parse_value(), Ok(expr.clone()));
+    assert!(!expr.to_string().contains('\n'));
+    assert_eq!(PsonParser::new(expr.to_string().chars()).parse_value(), Ok(expr));
+}
+
+#[test]
+fn number_grammar_test(){
+    let cases = [
+        ("0", Expr::Integer(0)),
+        ("-42", Expr::Integer(-42)),
+        ("+7", Expr::Integer(7)),
+        ("1_000_000", Expr::Integer(1_000_000)),
+        ("0xFF_ff", Expr::Integer(0xffff)),
+        ("-0o17", Expr::Integer(-0o17)),
+        ("0b1010", Expr::Integer(10)),
+        ("1.5", Expr::Float(1.5)),
+        ("-0.25e-2", Expr::Float(-0.0025)),
+        ("6E2", Expr::Float(600.0)),
+        ("1_0.0_1", Expr::Float(10.01)),
+        ("340282366920938463463374607431768211456", Expr::Float(2f64.powi(128))),
+        ("0x1_0000_0000_0000_0000_0000_0000_0000_0000", Expr::Float(2f64.powi(128))),
+        (".inf", Expr::Float(f64::INFINITY)),
+        ("-.inf", Expr::Float(f64::NEG_INFINITY)),
+    ];
+    for (word, expected) in cases {
+        assert_eq!(Expr::from(word), expected, "{}", word);
+    }
+    assert!(Expr::from(".nan").as_float().unwrap().is_nan());
+
+    let strict = |text: &str| PsonParser::new(text.chars()).with_options(ParseOptions::new().with_strict(true)).parse_value();
+    let reparse = |text: String| strict(&text).unwrap();
+    let words = ["inf", "NaN", "infinity", "1e400", "1_000_", "1__0", "_1", "007", "1.", ".5", "1e", "0x", "0xG", "1.2.3", "12ab", "-", "+.nan"];
+    for word in words {
+        assert_eq!(Expr::from(word), Expr::String(word.to_string()), "{}", word);
+        assert_eq!(reparse(Expr::String(word.to_string()).to_string()), Expr::String(word.to_string()));
+    }
+
+    assert_eq!(strict("[inf 1.5 \"1e400\" {007 x}]").map(|_| ()), Ok(()));
+    let error = strict("[1 1e400]").unwrap_err();
+    assert_eq!((error.kind, error.position.offset), (ErrorKind::InvalidNumber("1e400".to_string()), 3));
+    assert_eq!(strict("-.5").unwrap_err().kind, ErrorKind::InvalidNumber("-.5".to_string()));
+    assert_eq!(PsonParser::new("007".chars()).parse_value(), Ok(Expr::String("007".to_string())));
+
+    let floats = Expr::Array([1.0, 1e20, -1e-7, f64::INFINITY, f64::NEG_INFINITY].map(Expr::Float).to_vec());
+    assert_eq!(floats.to_string(), "[1.0 1e20 -1e-7 .inf -.inf]");
+    assert_eq!(reparse(floats.to_string()), floats);
+    assert_eq!(Expr::Float(f64::NAN).to_string(), ".nan");
+    assert_eq!(Expr::Float(f64::NAN).to_canonical_string(), ".nan");
+}
+
+#[test]
+fn big_numbers_test(){
+    let big = "340_282_366_920_938_463_463_374_607_431_768_211_457";
+    let text = format!("[{} -0x1_0000_0000_0000_0000_0000_0000_0000_0000 6.99 1e400 12]", big);
+    let with_numbers = |numbers| ParseOptions::new().with_numbers(numbers);
+    let parse = |numbers| PsonParser::new(text.chars()).with_options(with_numbers(numbers)).parse_value();
+
+    let expr = parse(NumberOptions::new()).unwrap();
+    assert_eq!(expr.as_array().unwrap()[..3], [Expr::Float(2f64.powi(128)), Expr::Float(-(2f64.powi(128))), Expr::Float(6.99)]);
+    assert_eq!(expr.as_array().unwrap()[3], Expr::String("1e400".to_string()));
+
+    let exact = NumberOptions::new()
+        .with_integer_overflow(IntegerOverflow::BigInt)
+        .with_exact_decimals(true);
+    let expr = parse(exact).unwrap();
+    assert_eq!(expr, Expr::Array(vec![
+        Expr::BigInt(big.to_string()),
+        Expr::BigInt("-0x1_0000_0000_0000_0000_0000_0000_0000_0000".to_string()),
+        Expr::Decimal("6.99".to_string()),
+        Expr::Decimal("1e400".to_string()),
+        Expr::Integer(12),
+    ]));
+    assert_eq!(expr.as_array().unwrap()[2].as_decimal(), Some("6.99".to_string()));
+    assert_eq!(expr.to_string(), text);
+    assert_eq!(
+        expr.to_canonical_string(),
+        "[340282366920938463463374607431768211457 -340282366920938463463374607431768211456 699e-2 1e400 12]",
+    );
+    let canonical = PsonParser::new(expr.to_canonical_string().chars()).with_options(with_numbers(exact)).parse_value();
+    assert_eq!(canonical.as_ref(), Ok(&expr));
+    assert_eq!(canonical.unwrap().to_canonical_string(), expr.to_canonical_string());
+
+    // Spellings of one number are equal, hash alike and share a canonical form.
+    use std::hash::BuildHasher;
+    let state = std::collections::hash_map::RandomState::new();
+    for (a, b) in [
+        (Expr::BigInt("0x1_00".to_string()), Expr::Integer(256)),
+        (Expr::BigInt(big.to_string()), Expr::BigInt(format!("+{}", big.replace('_', "")))),
+        (Expr::BigInt("-0b0".to_string()), Expr::BigInt("0".to_string())),
+        (Expr::Decimal("6.990".to_string()), Expr::Decimal("699e-2".to_string())),
+        (Expr::Decimal("0.10".to_string()), Expr::Decimal("1_0.0e-2".to_string())),
+        (Expr::Decimal("-0.0".to_string()), Expr::Decimal("0e5".to_string())),
+    ] {
+        assert_eq!(a, b);
+        assert_eq!(state.hash_one(&a), state.hash_one(&b));
+        assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+    }
+    assert_eq!(Expr::Decimal("-120.5e+3".to_string()).to_canonical_string(), "-1205e2");
+    assert_ne!(Expr::Decimal("6.99".to_string()), Expr::Decimal("6.98".to_string()));
+    assert_ne!(Expr::Decimal("1e400".to_string()), Expr::Decimal("1e401".to_string()));
+    assert_eq!(PsonParser::new(expr.to_pretty_string().chars()).with_options(with_numbers(exact)).parse_value(), Ok(expr));
+
+    let forbid = NumberOptions::new().with_integer_overflow(IntegerOverflow::Error);
+    let error = parse(forbid).unwrap_err();
+    assert_eq!((error.kind, error.position.offset), (ErrorKind::IntegerOverflow(big.to_string()), 1));
+    let (expr, errors) = PsonParser::new("1 99999999999999999999999999999999999999999 2".chars())
+        .with_options(with_numbers(forbid))
+        .parse_recovering();
+    assert_eq!(expr, Expr::Array(vec![Expr::Integer(1), Expr::Float(1e41), Expr::Integer(2)]));
+    assert_eq!(errors.len(), 1);
+
+    let reader = PsonReader::new("{p 0.10}".as_bytes()).with_options(with_numbers(exact));
+    let decimal = Expr::Decimal("0.10".to_string());
+    assert_eq!(reader.parse().unwrap().as_array().unwrap()[0].as_map().unwrap().get("p"), Some(&decimal));
+}
+
+#[test]
+fn parse_options_test(){
+    let text = "{a 1 a 0x_1}";
+    let options = ParseOptions::new()
+        .with_duplicate_keys(DuplicateKeys::LastWins)
+        .with_strict(true);
+
+    // Every front end applies the same options, and so rejects `0x_1` strictly.
+    let strict_error = ErrorKind::InvalidNumber("0x_1".to_string());
+    let parse = |options| PsonParser::new(text.chars()).with_options(options).parse();
+    assert_eq!(parse(options).unwrap_err().kind, strict_error);
+    assert_eq!(PsonParser::new(text.chars()).with_options(options).parse_value().unwrap_err().kind, strict_error);
+    assert_eq!(PsonParser::new(text.chars()).with_options(options).values().next().unwrap().unwrap_err().kind, strict_error);
+    assert_eq!(PushParser::new().with_options(options).feed_str(text).unwrap_err().kind, strict_error);
+    assert_eq!(PsonReader::new(text.as_bytes()).with_options(options).parse().unwrap_err().kind, strict_error);
+    assert_eq!(EventReader::new(text.chars()).with_options(options).find_map(Result::err).unwrap().kind, strict_error);
+
+    // The last `a` wins, as a string since it is not a valid number.
+    let lenient = options.with_strict(false);
+    let expected = PsonParser::new("{a \"0x_1\"}".chars()).parse_value().unwrap();
+    assert_eq!(PsonParser::new(text.chars()).with_options(lenient).parse_value(), Ok(expected.clone()));
+    assert_eq!(PsonParser::new(text.chars()).with_options(lenient).values().next(), Some(Ok(expected.clone())));
+    assert_eq!(PsonReader::new(text.as_bytes()).with_options(lenient).parse(), Ok(Expr::Array(vec![expected])));
+    assert_eq!(PsonParser::new(text.chars()).parse_value().unwrap_err().kind, ErrorKind::DuplicateKey("a".to_string()));
+}
+
 #[test]
 fn schema_test(){
     pson_schemas!{
@@ -0,0 +1,57 @@
+use std::str::Chars;
+
+use crate::error::{Position, PsonError};
+use crate::event::EventReader;
+use crate::expr::Expr;
+use crate::frame::TreeBuilder;
+use crate::options::ParseOptions;
+
+/// Iterator over the top-level values of a document, yielding each one as
+/// soon as it is closed instead of collecting them into the root array.
+pub struct Values<'a> {
+    events: EventReader<'a>,
+    builder: TreeBuilder<'a, Expr>,
+}
+
+impl<'a> Values<'a> {
+    pub fn new(text: Chars<'a>) -> Values<'a> {
+        Values {
+            events: EventReader::new(text),
+            builder: TreeBuilder::new(),
+        }
+    }
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.events = self.events.with_options(options);
+        self.builder.duplicate_keys = options.duplicate_keys;
+        self
+    }
+    pub fn position(&self) -> Position {
+        self.events.position()
+    }
+    /// Like `next`, but also gives the position where the value starts.
+    pub(crate) fn next_positioned(&mut self) -> Option<Result<(Expr, Position), PsonError>> {
+        let mut start = None;
+        loop {
+            let (event, position) = match self.events.next()? {
+                Ok(event) => event,
+                Err(error) => return Some(Err(error)),
+            };
+            if self.builder.depth() == 1 {
+                start = Some(position);
+            }
+            self.builder.event(event);
+            if self.builder.depth() == 1 {
+                let value = self.builder.pop_value().expect("a top-level value was just completed");
+                return Some(Ok((value, start.expect("value start was recorded"))));
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = Result<Expr, PsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_positioned().map(|value| value.map(|(expr, _)| expr))
+    }
+}
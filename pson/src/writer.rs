@@ -0,0 +1,338 @@
+use std::{borrow::Cow, error::Error, fmt, io};
+
+use crate::expr::Expr;
+use crate::expr_ref::ExprRef;
+use crate::frame::FrameKind;
+use crate::number::{self, looks_numeric};
+use crate::pretty::PrettyPrinter;
+
+/// Whether `s` reads back as the same string when written without quotes.
+/// Control characters and any whitespace are quoted too, so a written
+/// value never spans several lines.
+pub(crate) fn is_bareword(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with("//")
+        && !s.starts_with("/*")
+        && !s.contains(|c: char| c.is_whitespace() || c.is_control() || matches!(c, '[' | ']' | '{' | '}' | '"'))
+        && matches!(ExprRef::from_bareword(Cow::Borrowed(s)), ExprRef::String(_))
+        && !looks_numeric(s)
+}
+
+/// The number of `#` a raw string holding `s` needs, if it can hold it and
+/// is no longer than the quoted form.
+fn raw_hashes(s: &str) -> Option<usize> {
+    let escapes = s.chars().filter(|&c| c == '"' || c == '\\').count();
+    if escapes == 0 || s.contains(|c: char| c.is_control()) {
+        return None;
+    }
+    let mut hashes = 0;
+    while s.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    // The `r` and the `#` on both sides against one backslash per escape.
+    (2 * hashes < escapes).then_some(hashes)
+}
+
+/// Whether `s` is best written as a block string when laid out over
+/// several lines: it has line breaks, and nothing a block cannot hold.
+pub(crate) fn is_block(s: &str) -> bool {
+    s.contains('\n') && !s.contains("\"\"\"") && !s.contains(|c: char| c.is_control() && c != '\n' && c != '\t')
+}
+
+/// Writes a string, quoting it only when needed, and then as a raw string
+/// when that saves escaping backslashes and quotes.
+pub(crate) fn write_string(f: &mut impl fmt::Write, s: &str) -> fmt::Result {
+    if is_bareword(s) {
+        return f.write_str(s);
+    }
+    if let Some(hashes) = raw_hashes(s) {
+        let hashes = "#".repeat(hashes);
+        return write!(f, "r{}\"{}\"{}", hashes, s, hashes);
+    }
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            c if c.is_ascii_control() => write!(f, "\\x{:02x}", c as u32)?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// Writes a float so that it reads back as a float: `1.0` rather than `1`,
+/// exponents rather than long runs of digits, and `.inf` or `.nan` for
+/// the values that are not finite.
+pub(crate) fn write_float(f: &mut impl fmt::Write, n: f64) -> fmt::Result {
+    match n {
+        n if n.is_nan() => f.write_str(".nan"),
+        f64::INFINITY => f.write_str(".inf"),
+        f64::NEG_INFINITY => f.write_str("-.inf"),
+        n => write!(f, "{:?}", n),
+    }
+}
+
+/// Writes a non-container value; containers are left to the caller.
+pub(crate) fn write_scalar(f: &mut impl fmt::Write, expr: &Expr) -> fmt::Result {
+    match expr {
+        Expr::Null() => f.write_char('N'),
+        Expr::Boolean(true) => f.write_char('T'),
+        Expr::Boolean(false) => f.write_char('F'),
+        Expr::Integer(n) => write!(f, "{}", n),
+        Expr::Float(n) => write_float(f, *n),
+        Expr::BigInt(s) | Expr::Decimal(s) => f.write_str(s),
+        Expr::String(s) => write_string(f, s),
+        Expr::Array(_) | Expr::Map(_) => unreachable!("containers are written by the caller"),
+    }
+}
+
+/// The integer a float stands for in canonical form, if it is integral.
+pub(crate) fn integral(n: f64) -> Option<i128> {
+    // The upper bound, 2^127, is exactly representable but one past i128::MAX.
+    let in_range = n >= i128::MIN as f64 && n < i128::MAX as f64;
+    (n.fract() == 0.0 && in_range).then_some(n as i128)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Compact,
+    /// Sorted keys, floats that hold integers written as integers, and big
+    /// integers and decimals in their normal forms.
+    Canonical,
+}
+
+/// Writes `expr` on a single line with single spaces between items.
+pub(crate) fn write_compact(f: &mut impl fmt::Write, expr: &Expr) -> fmt::Result {
+    write_inline(f, expr, Style::Compact)
+}
+
+/// Writes the one text all values equal to `expr` share.
+pub(crate) fn write_canonical(f: &mut impl fmt::Write, expr: &Expr) -> fmt::Result {
+    write_inline(f, expr, Style::Canonical)
+}
+
+fn write_inline(f: &mut impl fmt::Write, expr: &Expr, style: Style) -> fmt::Result {
+    match expr {
+        Expr::Array(items) => {
+            f.write_char('[')?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(' ')?;
+                }
+                write_inline(f, item, style)?;
+            }
+            f.write_char(']')
+        }
+        Expr::Map(entries) => {
+            let mut entries: Vec<_> = entries.iter().collect();
+            if style != Style::Compact {
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+            }
+            f.write_char('{')?;
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    f.write_char(' ')?;
+                }
+                write_string(f, key)?;
+                f.write_char(' ')?;
+                write_inline(f, value, style)?;
+            }
+            f.write_char('}')
+        }
+        Expr::Float(n) if style == Style::Canonical => match integral(*n) {
+            Some(n) => write!(f, "{}", n),
+            None => write_float(f, *n),
+        },
+        Expr::BigInt(s) if style == Style::Canonical => f.write_str(&number::canonical_integer(s)),
+        Expr::Decimal(s) if style == Style::Canonical => f.write_str(&number::canonical_decimal(s)),
+        _ => write_scalar(f, expr),
+    }
+}
+
+#[derive(Debug)]
+pub enum WriteError {
+    Io(io::Error),
+    /// A value was given inside a map where a key was expected.
+    ExpectedKey,
+    /// A key was given outside a map or right after another key.
+    UnexpectedKey,
+    /// `end` was called with no open array or map.
+    NothingToEnd,
+    /// A map was ended right after a key, leaving it without a value.
+    MissingValue,
+    /// `finish` was called with this many arrays or maps still open.
+    Unclosed(usize),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Io(e) => write!(f, "I/O error: {}", e),
+            WriteError::ExpectedKey => write!(f, "expected a map key, found a value"),
+            WriteError::UnexpectedKey => write!(f, "unexpected key outside of a map key position"),
+            WriteError::NothingToEnd => write!(f, "no array or map to end"),
+            WriteError::MissingValue => write!(f, "map key without a value"),
+            WriteError::Unclosed(n) => write!(f, "{} unclosed arrays or maps", n),
+        }
+    }
+}
+
+impl Error for WriteError {}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+struct OpenContainer {
+    kind: FrameKind,
+    len: usize,
+    /// Width of the key awaiting its value, if any.
+    key: Option<usize>,
+}
+
+/// Writes a document value by value, without building an [`Expr`] first.
+///
+/// Top-level values go on separate lines. Nesting is checked as calls come
+/// in: a misplaced call fails without writing anything.
+pub struct PsonWriter<W: io::Write> {
+    writer: W,
+    pretty: Option<PrettyPrinter>,
+    stack: Vec<OpenContainer>,
+    top_level: usize,
+    buffer: String,
+}
+
+impl<W: io::Write> PsonWriter<W> {
+    /// A writer putting each top-level value on one line.
+    pub fn new(writer: W) -> Self {
+        PsonWriter {
+            writer,
+            pretty: None,
+            stack: Vec::new(),
+            top_level: 0,
+            buffer: String::new(),
+        }
+    }
+    /// A writer breaking every array and map over several lines; values
+    /// passed whole are laid out by `printer`. Maps opened with
+    /// [`PsonWriter::begin_map`] never have their values aligned, whatever
+    /// [`PrettyPrinter::with_aligned_values`] says: their keys are written
+    /// before the later, longer ones are known.
+    pub fn pretty(writer: W, printer: PrettyPrinter) -> Self {
+        PsonWriter {
+            pretty: Some(printer),
+            ..PsonWriter::new(writer)
+        }
+    }
+    fn newline(&mut self, depth: usize) {
+        self.buffer.push('\n');
+        if let Some(printer) = &self.pretty {
+            printer.indent(&mut self.buffer, depth).expect("writing to a String");
+        }
+    }
+    /// Writes the separator before the next item, returning the column
+    /// the item starts at in pretty mode.
+    fn separate(&mut self, is_key: bool) -> Result<usize, WriteError> {
+        let depth = self.stack.len();
+        let indent = self.pretty.as_ref().map_or(0, |printer| depth * printer.indent_width());
+        let Some(top) = self.stack.last_mut() else {
+            if is_key {
+                return Err(WriteError::UnexpectedKey);
+            }
+            if self.top_level > 0 {
+                self.buffer.push('\n');
+            }
+            self.top_level += 1;
+            return Ok(0);
+        };
+        if let Some(key) = top.key {
+            if is_key {
+                return Err(WriteError::UnexpectedKey);
+            }
+            top.key = None;
+            self.buffer.push(' ');
+            return Ok(indent + key + 1);
+        }
+        match (top.kind, is_key) {
+            (FrameKind::Array, true) => return Err(WriteError::UnexpectedKey),
+            (FrameKind::Map, false) => return Err(WriteError::ExpectedKey),
+            _ => {}
+        }
+        top.len += 1;
+        match (top.len, self.pretty.is_some()) {
+            (_, true) => self.newline(depth),
+            (1, false) => {}
+            (_, false) => self.buffer.push(' '),
+        }
+        Ok(indent)
+    }
+    fn flush_buffer(&mut self) -> Result<(), WriteError> {
+        self.writer.write_all(self.buffer.as_bytes())?;
+        self.buffer.clear();
+        Ok(())
+    }
+    fn begin(&mut self, kind: FrameKind) -> Result<(), WriteError> {
+        self.separate(false)?;
+        self.buffer.push(match kind {
+            FrameKind::Array => '[',
+            FrameKind::Map => '{',
+        });
+        self.stack.push(OpenContainer { kind, len: 0, key: None });
+        self.flush_buffer()
+    }
+    pub fn begin_array(&mut self) -> Result<(), WriteError> {
+        self.begin(FrameKind::Array)
+    }
+    pub fn begin_map(&mut self) -> Result<(), WriteError> {
+        self.begin(FrameKind::Map)
+    }
+    pub fn key(&mut self, key: &str) -> Result<(), WriteError> {
+        self.separate(true)?;
+        let start = self.buffer.len();
+        write_string(&mut self.buffer, key).expect("writing to a String");
+        let width = self.buffer[start..].chars().count();
+        self.stack.last_mut().expect("keys are only accepted in maps").key = Some(width);
+        self.flush_buffer()
+    }
+    pub fn value(&mut self, value: &Expr) -> Result<(), WriteError> {
+        let column = self.separate(false)?;
+        match &self.pretty {
+            Some(printer) => printer.write(&mut self.buffer, value, self.stack.len(), column),
+            None => write_compact(&mut self.buffer, value),
+        }
+        .expect("writing to a String");
+        self.flush_buffer()
+    }
+    /// Closes the innermost open array or map.
+    pub fn end(&mut self) -> Result<(), WriteError> {
+        let top = self.stack.last().ok_or(WriteError::NothingToEnd)?;
+        if top.key.is_some() {
+            return Err(WriteError::MissingValue);
+        }
+        let top = self.stack.pop().expect("checked above");
+        if self.pretty.is_some() && top.len > 0 {
+            self.newline(self.stack.len());
+        }
+        self.buffer.push(top.kind.closer());
+        self.flush_buffer()
+    }
+    /// Checks that everything was closed, ends the last line and hands back
+    /// the underlying writer.
+    pub fn finish(mut self) -> Result<W, WriteError> {
+        if !self.stack.is_empty() {
+            return Err(WriteError::Unclosed(self.stack.len()));
+        }
+        if self.top_level > 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
@@ -0,0 +1,328 @@
+use std::{borrow::Cow, ops::Range};
+
+use pson::{ErrorKind, ExprRef, Lexer, PsonError, PsonParser, TokenKind};
+use serde_json::{json, Value as Json};
+
+use crate::schema::{bareword, describe, Schema};
+use crate::syntax::{path_at, LineIndex, Value, ValueKind};
+
+/// Widest inline container the formatter keeps on one line.
+const MAX_WIDTH: usize = 80;
+
+fn range(index: &LineIndex, span: Range<usize>) -> Json {
+    let (start_line, start_character) = index.position(span.start);
+    let (end_line, end_character) = index.position(span.end);
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+/// The span of the character at `offset`, or an empty one at the end.
+fn char_span(text: &str, offset: usize) -> Range<usize> {
+    let width = text[offset..].chars().next().map_or(0, char::len_utf8);
+    offset..offset + width
+}
+
+pub fn parse_errors(text: &str) -> Vec<PsonError> {
+    PsonParser::new(text.chars()).parse_recovering().1
+}
+
+pub fn diagnostics(uri: &str, text: &str, schema: Option<&Result<Schema, String>>) -> Vec<Json> {
+    let index = LineIndex::new(text);
+    let mut diagnostics: Vec<Json> = parse_errors(text)
+        .into_iter()
+        .map(|error| {
+            let mut diagnostic = json!({
+                "range": range(&index, char_span(text, error.position.offset)),
+                "severity": 1,
+                "source": "pson",
+                "message": error.kind.to_string(),
+            });
+            if let Some(opened) = error.opened_at {
+                diagnostic["relatedInformation"] = json!([{
+                    "location": { "uri": uri, "range": range(&index, char_span(text, opened.offset)) },
+                    "message": match error.kind {
+                        ErrorKind::DuplicateKey(_) => "first defined here",
+                        _ => "opened here",
+                    },
+                }]);
+            }
+            diagnostic
+        })
+        .collect();
+    let schema_errors = match schema {
+        Some(Ok(schema)) => schema.validate(&crate::syntax::parse(text)),
+        Some(Err(message)) => vec![(0..0, format!("invalid schema: {}", message))],
+        None => Vec::new(),
+    };
+    diagnostics.extend(schema_errors.into_iter().map(|(span, message)| {
+        json!({
+            "range": range(&index, span),
+            "severity": 1,
+            "source": "pson-schema",
+            "message": message,
+        })
+    }));
+    diagnostics
+}
+
+fn symbol_kind(value: &Value) -> u32 {
+    match describe(value) {
+        "map" => 19,
+        "array" => 18,
+        "integer" | "float" => 16,
+        "boolean" => 17,
+        "null" => 21,
+        _ => 15,
+    }
+}
+
+fn symbol(index: &LineIndex, name: String, selection: Range<usize>, value: &Value) -> Json {
+    json!({
+        "name": name,
+        "kind": symbol_kind(value),
+        "range": range(index, selection.start..value.span.end),
+        "selectionRange": range(index, selection),
+        "children": symbols_in(index, value),
+    })
+}
+
+/// Symbols for map entries and for containers nested in arrays.
+fn symbols_in(index: &LineIndex, value: &Value) -> Vec<Json> {
+    match &value.kind {
+        ValueKind::Scalar { .. } => Vec::new(),
+        ValueKind::Array(items) => array_symbols(index, items),
+        ValueKind::Map(children) => children
+            .chunks_exact(2)
+            .map(|entry| {
+                let name = entry[0].text().unwrap_or_default().to_string();
+                symbol(index, name, entry[0].span.clone(), &entry[1])
+            })
+            .collect(),
+    }
+}
+
+fn array_symbols(index: &LineIndex, items: &[Value]) -> Vec<Json> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !matches!(item.kind, ValueKind::Scalar { .. }))
+        .map(|(i, item)| symbol(index, format!("[{}]", i), item.span.start..item.span.start + 1, item))
+        .collect()
+}
+
+pub fn document_symbols(text: &str) -> Vec<Json> {
+    array_symbols(&LineIndex::new(text), &crate::syntax::parse(text))
+}
+
+pub fn folding_ranges(text: &str) -> Vec<Json> {
+    fn walk(index: &LineIndex, values: &[Value], out: &mut Vec<Json>) {
+        for value in values {
+            if let ValueKind::Scalar { .. } = value.kind {
+                continue;
+            }
+            let (start, _) = index.position(value.span.start);
+            let (end, _) = index.position(value.span.end);
+            if end > start {
+                out.push(json!({ "startLine": start, "endLine": end }));
+            }
+            walk(index, value.children(), out);
+        }
+    }
+    let index = LineIndex::new(text);
+    let mut ranges = Vec::new();
+    walk(&index, &crate::syntax::parse(text), &mut ranges);
+    for comment in Lexer::new(text).filter(|token| token.kind == TokenKind::Comment) {
+        let (start, _) = index.position(comment.span.start);
+        let (end, _) = index.position(comment.span.end);
+        if end > start {
+            ranges.push(json!({ "startLine": start, "endLine": end, "kind": "comment" }));
+        }
+    }
+    ranges
+}
+
+fn write_inline(text: &str, value: &Value, out: &mut String) {
+    let (open, close) = match &value.kind {
+        ValueKind::Scalar { .. } => return out.push_str(&text[value.span.clone()]),
+        ValueKind::Array(_) => ('[', ']'),
+        ValueKind::Map(_) => ('{', '}'),
+    };
+    out.push(open);
+    for (i, child) in value.children().iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        write_inline(text, child, out);
+    }
+    out.push(close);
+}
+
+/// Lays out values one per line, carrying comments over verbatim.
+struct Formatter<'t> {
+    text: &'t str,
+    indent: &'t str,
+    /// Spans of the comments, in source order.
+    comments: Vec<Range<usize>>,
+    out: String,
+}
+
+impl Formatter<'_> {
+    /// The comments lying within `range`.
+    fn comments(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let first = self.comments.partition_point(|span| span.start < range.start);
+        self.comments[first..].iter().take_while(|span| span.end <= range.end).cloned().collect()
+    }
+    fn new_line(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out.push_str(&self.indent.repeat(depth));
+    }
+    /// A space before the next token, unless a line was just started.
+    fn separate(&mut self) {
+        if !self.out.ends_with([' ', '\t', '\n']) {
+            self.out.push(' ');
+        }
+    }
+    /// Writes the comments in `range`. One that started on the source line
+    /// being written stays on it; any other gets a line of its own.
+    fn write_comments(&mut self, range: Range<usize>, depth: usize) {
+        let mut end = range.start;
+        for span in self.comments(range) {
+            match self.out.is_empty() || self.text[end..span.start].contains('\n') {
+                true => self.new_line(depth),
+                false => self.out.push(' '),
+            }
+            self.out.push_str(&self.text[span.clone()]);
+            end = span.end;
+        }
+    }
+    /// Writes `children` at `depth`, `per_line` of them to a line, along with
+    /// the comments of `range` around and between them.
+    fn write_lines(&mut self, children: &[Value], per_line: usize, range: Range<usize>, depth: usize) {
+        let mut end = range.start;
+        for group in children.chunks(per_line) {
+            self.write_comments(end..group[0].span.start, depth);
+            self.new_line(depth);
+            for (i, child) in group.iter().enumerate() {
+                if i > 0 {
+                    for span in self.comments(group[i - 1].span.end..child.span.start) {
+                        self.separate();
+                        self.out.push_str(&self.text[span.clone()]);
+                        if self.text[span].starts_with("//") {
+                            self.new_line(depth + 1);
+                        }
+                    }
+                    self.separate();
+                }
+                self.write_value(child, depth);
+            }
+            end = group[group.len() - 1].span.end;
+        }
+        self.write_comments(end..range.end, depth);
+    }
+    fn write_value(&mut self, value: &Value, depth: usize) {
+        let mut inline = String::new();
+        write_inline(self.text, value, &mut inline);
+        let flat = value.children().iter().all(|child| matches!(child.kind, ValueKind::Scalar { .. }));
+        let commented = !self.comments(value.span.clone()).is_empty();
+        if flat && !commented && depth * self.indent.len() + inline.chars().count() <= MAX_WIDTH {
+            return self.out.push_str(&inline);
+        }
+        let per_line = match value.kind {
+            ValueKind::Map(_) => 2,
+            _ => 1,
+        };
+        self.out.push_str(&inline[..1]);
+        self.write_lines(value.children(), per_line, value.span.start + 1..value.span.end - 1, depth + 1);
+        self.new_line(depth);
+        self.out.push_str(&inline[inline.len() - 1..]);
+    }
+}
+
+/// Reindents `text`, one top-level value per line, keeping its comments.
+/// Returns `None` for input with errors, which the formatter would
+/// otherwise mangle; those are reported as diagnostics.
+pub fn format(text: &str, indent: &str) -> Option<String> {
+    if !parse_errors(text).is_empty() {
+        return None;
+    }
+    let comments = Lexer::new(text)
+        .filter(|token| token.kind == TokenKind::Comment)
+        .map(|token| token.span)
+        .collect();
+    let mut formatter = Formatter { text, indent, comments, out: String::with_capacity(text.len()) };
+    formatter.write_lines(&crate::syntax::parse(text), 1, 0..text.len(), 0);
+    // Every line was started with a line break; the first one moves to the end.
+    Some(match formatter.out.strip_prefix('\n') {
+        Some(lines) => format!("{}\n", lines),
+        None => formatter.out,
+    })
+}
+
+pub fn hover(text: &str, offset: usize, schema: Option<&Schema>) -> Option<Json> {
+    let values = crate::syntax::parse(text);
+    let path = path_at(&values, offset);
+    let &(value, _) = path.last()?;
+    let mut contents = match describe(value) {
+        "map" => format!("`Expr::Map` ({} entries)", value.children().len() / 2),
+        "array" => format!("`Expr::Array` ({} items)", value.children().len()),
+        "integer" | "float" => {
+            // Numbers an `i128` or `f64` cannot hold read as the parser's `NumberOptions` say.
+            let text = value.text().unwrap_or_default();
+            let variant = |expr: ExprRef| match expr {
+                ExprRef::Integer(_) => "Expr::Integer",
+                ExprRef::Float(_) => "Expr::Float",
+                ExprRef::BigInt(_) => "Expr::BigInt",
+                ExprRef::Decimal(_) => "Expr::Decimal",
+                _ => "Expr::String",
+            };
+            match (variant(ExprRef::from_bareword(Cow::Borrowed(text))), variant(bareword(text))) {
+                (default, exact) if default == exact => format!("`{}`", exact),
+                (default, exact) => format!("`{}`, or `{}` with exact `NumberOptions`", default, exact),
+            }
+        }
+        "boolean" => "`Expr::Boolean`".to_string(),
+        "null" => "`Expr::Null`".to_string(),
+        _ => "`Expr::String`".to_string(),
+    };
+    let segments: Vec<_> = path.iter().map(|&(_, segment)| segment).collect();
+    if let Some(ty) = schema.and_then(|schema| schema.type_at(&segments)) {
+        contents.push_str(&format!("\n\nschema: `{}`", ty));
+    }
+    let index = LineIndex::new(text);
+    Some(json!({
+        "contents": { "kind": "markdown", "value": contents },
+        "range": range(&index, value.span.clone()),
+    }))
+}
+
+/// Offers the schema's missing keys when the cursor is at a key position
+/// of a map. `path_at` stops at the map when the cursor is on a key.
+pub fn completion(text: &str, offset: usize, schema: &Schema) -> Vec<Json> {
+    let values = crate::syntax::parse(text);
+    let path = path_at(&values, offset);
+    let Some(&(map, _)) = path.last().filter(|(value, _)| matches!(value.kind, ValueKind::Map(_))) else {
+        return Vec::new();
+    };
+    let segments: Vec<_> = path.iter().map(|&(_, segment)| segment).collect();
+    let Some(fields) = schema.type_at(&segments).and_then(|ty| schema.fields(ty)) else {
+        return Vec::new();
+    };
+    let children = map.children();
+    let at = children.iter().filter(|child| child.span.start < offset && !child.contains(offset)).count();
+    if at % 2 == 1 {
+        return Vec::new();
+    }
+    let present: Vec<&str> = children
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i % 2 == 0 && i != at)
+        .filter_map(|(_, key)| key.text())
+        .collect();
+    fields
+        .iter()
+        .filter(|(name, _)| !present.contains(&name.as_str()))
+        .map(|(name, ty)| json!({ "label": name, "kind": 10, "detail": ty.to_string() }))
+        .collect()
+}
@@ -0,0 +1,72 @@
+mod features;
+mod schema;
+mod server;
+mod syntax;
+
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use serde_json::Value as Json;
+
+use server::Server;
+
+/// Reads one `Content-Length` framed message, or `None` at end of input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn main() -> ExitCode {
+    let (stdin, stdout) = (io::stdin(), io::stdout());
+    let (mut input, mut output) = (stdin.lock(), stdout.lock());
+    let mut server = Server::new();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => return ExitCode::FAILURE,
+            Err(error) => {
+                eprintln!("pson-lsp: {}", error);
+                return ExitCode::FAILURE;
+            }
+        };
+        if message["method"] == "exit" {
+            return match server.shut_down() {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            };
+        }
+        for reply in server.handle(&message) {
+            if let Err(error) = write_message(&mut output, &reply) {
+                eprintln!("pson-lsp: {}", error);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,292 @@
+use std::{borrow::Cow, fmt, ops::Range};
+
+use pson::{ExprRef, IntegerOverflow, NumberOptions, Segment};
+
+use crate::syntax::{Value, ValueKind};
+
+/// A type in the `pson_schemas!` syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    String,
+    Unsigned,
+    Int,
+    Float,
+    Bool,
+    Null,
+    /// `_Name`, referring to another declaration.
+    Ref(String),
+    Map(Vec<(String, Type)>),
+    Array(Box<Type>),
+    Option(Box<Type>),
+    Tuple(Vec<Type>),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::String => write!(f, "string"),
+            Type::Unsigned => write!(f, "unsgn"),
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Bool => write!(f, "bool"),
+            Type::Null => write!(f, "null"),
+            Type::Ref(name) => write!(f, "{}", name),
+            Type::Map(_) => write!(f, "map"),
+            Type::Array(inner) => write!(f, "array of {}", inner),
+            Type::Option(inner) => write!(f, "option of {}", inner),
+            Type::Tuple(items) => write!(f, "tuple of {}", items.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SchemaToken {
+    Open(char),
+    Close(char),
+    Ident(String),
+}
+
+impl fmt::Display for SchemaToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaToken::Open(c) | SchemaToken::Close(c) => write!(f, "`{}`", c),
+            SchemaToken::Ident(ident) => write!(f, "`{}`", ident),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<SchemaToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' | '(' | '{' => tokens.push(SchemaToken::Open(c)),
+            ']' | ')' | '}' => tokens.push(SchemaToken::Close(c)),
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+            c if c.is_whitespace() => {}
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(SchemaToken::Ident(ident));
+            }
+            _ => return Err(format!("unexpected character `{}`", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct SchemaParser {
+    tokens: std::vec::IntoIter<SchemaToken>,
+}
+
+impl SchemaParser {
+    fn ident(&mut self, what: &str) -> Result<String, String> {
+        match self.tokens.next() {
+            Some(SchemaToken::Ident(ident)) => Ok(ident),
+            _ => Err(format!("expected {}", what)),
+        }
+    }
+    fn expect(&mut self, expected: SchemaToken) -> Result<(), String> {
+        match self.tokens.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(format!("expected {}", expected)),
+        }
+    }
+    /// Parses types until the closing `close`.
+    fn types_until(&mut self, close: char) -> Result<Vec<Type>, String> {
+        let mut types = Vec::new();
+        while self.tokens.as_slice().first() != Some(&SchemaToken::Close(close)) {
+            types.push(self.ty()?);
+        }
+        self.tokens.next();
+        Ok(types)
+    }
+    fn ty(&mut self) -> Result<Type, String> {
+        match self.tokens.next() {
+            Some(SchemaToken::Ident(name)) => match name.as_str() {
+                "string" => Ok(Type::String),
+                "unsgn" => Ok(Type::Unsigned),
+                "int" => Ok(Type::Int),
+                "float" => Ok(Type::Float),
+                "bool" => Ok(Type::Bool),
+                "null" => Ok(Type::Null),
+                _ if name.starts_with('_') => Ok(Type::Ref(name[1..].to_string())),
+                _ => Err(format!("unrecognized primitive type `{}`", name)),
+            },
+            Some(SchemaToken::Open('[')) => {
+                let ty = match self.ident("map, array, option or tuple")?.as_str() {
+                    "map" => {
+                        self.expect(SchemaToken::Open('('))?;
+                        let mut fields = Vec::new();
+                        while self.tokens.as_slice().first() != Some(&SchemaToken::Close(')')) {
+                            fields.push((self.ident("a key")?, self.ty()?));
+                        }
+                        self.tokens.next();
+                        Type::Map(fields)
+                    }
+                    "array" => Type::Array(Box::new(self.ty()?)),
+                    "option" => Type::Option(Box::new(self.ty()?)),
+                    "tuple" => {
+                        self.expect(SchemaToken::Open('['))?;
+                        Type::Tuple(self.types_until(']')?)
+                    }
+                    kind => return Err(format!("unrecognized struct type `{}`", kind)),
+                };
+                self.expect(SchemaToken::Close(']'))?;
+                Ok(ty)
+            }
+            _ => Err("expected a type".to_string()),
+        }
+    }
+}
+
+/// A set of named declarations; the first one types the documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    decls: Vec<(String, Type)>,
+}
+
+impl Schema {
+    /// Parses the body of a `pson_schemas!` invocation.
+    pub fn parse(text: &str) -> Result<Schema, String> {
+        let mut parser = SchemaParser { tokens: tokenize(text)?.into_iter() };
+        let mut decls = Vec::new();
+        while !parser.tokens.as_slice().is_empty() {
+            decls.push((parser.ident("a declaration name")?, parser.ty()?));
+        }
+        Ok(Schema { decls })
+    }
+    pub fn root(&self) -> Option<&Type> {
+        self.decls.first().map(|(_, ty)| ty)
+    }
+    /// Follows references, giving up on unknown or cyclic names.
+    fn resolve<'s>(&'s self, mut ty: &'s Type) -> Option<&'s Type> {
+        for _ in 0..=self.decls.len() {
+            match ty {
+                Type::Ref(name) => ty = &self.decls.iter().find(|(decl, _)| decl == name)?.1,
+                _ => return Some(ty),
+            }
+        }
+        None
+    }
+    /// The type expected at `path`, as returned by [`crate::syntax::path_at`].
+    pub fn type_at(&self, path: &[Segment]) -> Option<&Type> {
+        let (first, rest) = path.split_first()?;
+        let mut ty = match first {
+            Segment::Index(_) => self.root()?,
+            Segment::Key(_) => return None,
+        };
+        for segment in rest {
+            let mut resolved = self.resolve(ty)?;
+            if let Type::Option(inner) = resolved {
+                resolved = self.resolve(inner)?;
+            }
+            ty = match (resolved, segment) {
+                (Type::Map(fields), Segment::Key(key)) => &fields.iter().find(|(name, _)| name == key)?.1,
+                (Type::Array(inner), Segment::Index(_)) => inner,
+                (Type::Tuple(items), Segment::Index(index)) => items.get(*index)?,
+                _ => return None,
+            };
+        }
+        Some(ty)
+    }
+    /// The fields of `ty` if it is a map, possibly behind references or an option.
+    pub fn fields<'s>(&'s self, ty: &'s Type) -> Option<&'s [(String, Type)]> {
+        match self.resolve(ty)? {
+            Type::Map(fields) => Some(fields),
+            Type::Option(inner) => self.fields(inner),
+            _ => None,
+        }
+    }
+    /// Checks every top-level value against the root declaration.
+    pub fn validate(&self, values: &[Value]) -> Vec<(Range<usize>, String)> {
+        let mut errors = Vec::new();
+        if let Some(root) = self.root() {
+            for value in values {
+                self.check(value, root, &mut errors);
+            }
+        }
+        errors
+    }
+    fn check(&self, value: &Value, ty: &Type, errors: &mut Vec<(Range<usize>, String)>) {
+        let Some(resolved) = self.resolve(ty) else {
+            return;
+        };
+        let found = describe(value);
+        let ok = match (resolved, &value.kind) {
+            (Type::Option(_), _) if found == "null" => true,
+            (Type::Option(inner), _) => return self.check(value, inner, errors),
+            (Type::String, _) => found == "string",
+            (Type::Unsigned, _) => found == "integer" && !value.text().is_some_and(|t| t.starts_with('-')),
+            (Type::Int, _) => found == "integer",
+            (Type::Float, _) => found == "integer" || found == "float",
+            (Type::Bool, _) => found == "boolean",
+            (Type::Null, _) => found == "null",
+            (Type::Array(inner), ValueKind::Array(items)) => {
+                items.iter().for_each(|item| self.check(item, inner, errors));
+                true
+            }
+            (Type::Tuple(types), ValueKind::Array(items)) => {
+                items.iter().zip(types).for_each(|(item, ty)| self.check(item, ty, errors));
+                if items.len() != types.len() {
+                    let message = format!("expected {} items, found {}", types.len(), items.len());
+                    errors.push((value.span.clone(), message));
+                }
+                true
+            }
+            (Type::Map(fields), ValueKind::Map(children)) => {
+                for entry in children.chunks(2) {
+                    let key = entry[0].text().unwrap_or_default();
+                    match (fields.iter().find(|(name, _)| name == key), entry.get(1)) {
+                        (Some((_, ty)), Some(value)) => self.check(value, ty, errors),
+                        (Some(_), None) => {}
+                        (None, _) => errors.push((entry[0].span.clone(), format!("unknown key `{}`", key))),
+                    }
+                }
+                let present = |name: &str| children.chunks(2).any(|entry| entry[0].text() == Some(name));
+                for (name, ty) in fields {
+                    let optional = matches!(self.resolve(ty), Some(Type::Option(_)));
+                    if !optional && !present(name) {
+                        let brace = value.span.start..value.span.start + 1;
+                        errors.push((brace, format!("missing key `{}`", name)));
+                    }
+                }
+                true
+            }
+            _ => false,
+        };
+        if !ok {
+            errors.push((value.span.clone(), format!("expected {}, found {}", ty, found)));
+        }
+    }
+}
+
+/// What a value reads as, in the words used by validation messages.
+pub fn describe(value: &Value) -> &'static str {
+    match &value.kind {
+        ValueKind::Array(_) => "array",
+        ValueKind::Map(_) => "map",
+        ValueKind::Scalar { quoted: true, .. } => "string",
+        ValueKind::Scalar { value, .. } => match bareword(value) {
+            ExprRef::Null() => "null",
+            ExprRef::Boolean(_) => "boolean",
+            ExprRef::Integer(_) | ExprRef::BigInt(_) => "integer",
+            ExprRef::Float(_) | ExprRef::Decimal(_) => "float",
+            _ => "string",
+        },
+    }
+}
+
+/// What an unquoted word reads as, keeping numbers that an `i128` or an
+/// `f64` cannot hold exactly, such as `1e400`, as numbers.
+pub fn bareword(text: &str) -> ExprRef<'_> {
+    let exact = NumberOptions::new()
+        .with_integer_overflow(IntegerOverflow::BigInt)
+        .with_exact_decimals(true);
+    ExprRef::from_bareword_with_numbers(Cow::Borrowed(text), exact)
+}
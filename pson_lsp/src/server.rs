@@ -0,0 +1,171 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use serde_json::{json, Value as Json};
+
+use crate::features;
+use crate::schema::Schema;
+use crate::syntax::LineIndex;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Turns a `file://` URI into a path, undoing percent-encoding.
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let hex = encoded.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (encoded[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// The schema associated with the document at `uri`: a sibling file with
+/// the same name and a `.pson-schema` extension, in `pson_schemas!` syntax.
+fn schema_for(uri: &str) -> Option<Result<Schema, String>> {
+    let path = uri_path(uri)?.with_extension("pson-schema");
+    fs::read_to_string(path).ok().map(|text| Schema::parse(&text))
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Whether a `shutdown` request came before `exit`.
+    pub fn shut_down(&self) -> bool {
+        self.shutdown
+    }
+    /// Handles one incoming message, returning the messages to send back.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+        if method.is_empty() {
+            // A response to a request of ours; we send none.
+            return Vec::new();
+        }
+        let response = match self.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        vec![response]
+    }
+    fn publish(&self, uri: &str) -> Json {
+        let diagnostics = match self.documents.get(uri) {
+            Some(text) => features::diagnostics(uri, text, schema_for(uri).as_ref()),
+            None => Vec::new(),
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()).and_then(|c| c["text"].as_str()) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+            }
+            "textDocument/didSave" => {}
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return Vec::new(),
+        }
+        vec![self.publish(&uri)]
+    }
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        if method == "initialize" {
+            return Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                    "documentSymbolProvider": true,
+                    "foldingRangeProvider": true,
+                    "documentFormattingProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "pson-lsp", "version": env!("CARGO_PKG_VERSION") },
+            }));
+        }
+        if method == "shutdown" {
+            self.shutdown = true;
+            return Ok(Json::Null);
+        }
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match self.documents.get(uri) {
+            Some(text) => text.as_str(),
+            None if method.starts_with("textDocument/") => {
+                return Err((INVALID_PARAMS, format!("unknown document {}", uri)));
+            }
+            None => return Err((METHOD_NOT_FOUND, format!("unhandled method {}", method))),
+        };
+        let offset = || {
+            let position = &params["position"];
+            let line = position["line"].as_u64().unwrap_or_default() as u32;
+            let character = position["character"].as_u64().unwrap_or_default() as u32;
+            LineIndex::new(text).offset(line, character)
+        };
+        let schema = || schema_for(uri).and_then(Result::ok);
+        Ok(match method {
+            "textDocument/documentSymbol" => json!(features::document_symbols(text)),
+            "textDocument/foldingRange" => json!(features::folding_ranges(text)),
+            "textDocument/hover" => json!(features::hover(text, offset(), schema().as_ref())),
+            "textDocument/completion" => match schema() {
+                Some(schema) => json!(features::completion(text, offset(), &schema)),
+                None => json!([]),
+            },
+            "textDocument/formatting" => {
+                let options = &params["options"];
+                let indent = match options["insertSpaces"].as_bool() {
+                    Some(false) => "\t".to_string(),
+                    _ => " ".repeat(options["tabSize"].as_u64().unwrap_or(2) as usize),
+                };
+                let index = LineIndex::new(text);
+                let (line, character) = index.position(text.len());
+                match features::format(text, &indent) {
+                    Some(formatted) if formatted != text => json!([{
+                        "range": {
+                            "start": { "line": 0, "character": 0 },
+                            "end": { "line": line, "character": character },
+                        },
+                        "newText": formatted,
+                    }]),
+                    _ => json!([]),
+                }
+            }
+            _ => return Err((METHOD_NOT_FOUND, format!("unhandled method {}", method))),
+        })
+    }
+}
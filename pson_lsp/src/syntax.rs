@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use pson::{Lexer, Segment, TokenKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    Scalar { value: String, quoted: bool },
+    Array(Vec<Value>),
+    Map(Vec<Value>),
+}
+
+/// A value with its byte span, as far as it could be made out of possibly
+/// broken input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub span: Range<usize>,
+    pub kind: ValueKind,
+    /// False for containers whose closing bracket is missing.
+    pub closed: bool,
+}
+
+impl Value {
+    pub fn children(&self) -> &[Value] {
+        match &self.kind {
+            ValueKind::Array(children) | ValueKind::Map(children) => children,
+            ValueKind::Scalar { .. } => &[],
+        }
+    }
+    /// The decoded text of a scalar.
+    pub fn text(&self) -> Option<&str> {
+        match &self.kind {
+            ValueKind::Scalar { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    /// Whether `offset` is inside this value, counting a cursor right after
+    /// a scalar but not one right after a closing bracket.
+    pub fn contains(&self, offset: usize) -> bool {
+        match self.kind {
+            ValueKind::Scalar { .. } => self.span.start <= offset && offset <= self.span.end,
+            _ if self.closed => self.span.start < offset && offset < self.span.end,
+            _ => self.span.start < offset && offset <= self.span.end,
+        }
+    }
+}
+
+fn close(kind: TokenKind, start: usize, children: Vec<Value>, end: usize, closed: bool) -> Value {
+    let kind = match kind {
+        TokenKind::OpenBrace => ValueKind::Map(children),
+        _ => ValueKind::Array(children),
+    };
+    Value { span: start..end, kind, closed }
+}
+
+/// Parses every top-level value, dropping stray closing brackets and
+/// running unclosed frames to the end of the input.
+pub fn parse(text: &str) -> Vec<Value> {
+    let mut stack: Vec<(TokenKind, usize, Vec<Value>)> = vec![(TokenKind::OpenBracket, 0, Vec::new())];
+    for token in Lexer::new(text) {
+        let value = match token.kind {
+            TokenKind::Whitespace | TokenKind::Comment => continue,
+            TokenKind::Bareword | TokenKind::QuotedString | TokenKind::RawString | TokenKind::BlockString => Value {
+                kind: ValueKind::Scalar {
+                    value: token.value.into_owned(),
+                    quoted: token.kind != TokenKind::Bareword,
+                },
+                span: token.span,
+                closed: true,
+            },
+            TokenKind::OpenBracket | TokenKind::OpenBrace => {
+                stack.push((token.kind, token.span.start, Vec::new()));
+                continue;
+            }
+            TokenKind::Close if stack.len() == 1 => continue,
+            TokenKind::Close => {
+                let (kind, start, children) = stack.pop().expect("checked above");
+                close(kind, start, children, token.span.end, true)
+            }
+        };
+        stack.last_mut().expect("root frame").2.push(value);
+    }
+    while stack.len() > 1 {
+        let (kind, start, children) = stack.pop().expect("checked above");
+        let value = close(kind, start, children, text.len(), false);
+        stack.last_mut().expect("root frame").2.push(value);
+    }
+    stack.pop().expect("root frame").2
+}
+
+/// The chain of values containing `offset`, outermost first, each with the
+/// segment leading to it from its parent. Map keys are not descended into.
+pub fn path_at(values: &[Value], offset: usize) -> Vec<(&Value, Segment<'_>)> {
+    let mut path = Vec::new();
+    let (mut children, mut is_map) = (values, false);
+    loop {
+        let found = children.iter().enumerate().find(|(_, child)| child.contains(offset));
+        let Some((index, child)) = found else {
+            return path;
+        };
+        let segment = match is_map {
+            false => Segment::Index(index),
+            true if index % 2 == 0 => return path,
+            true => match children[index - 1].text() {
+                Some(key) => Segment::Key(key),
+                None => return path,
+            },
+        };
+        path.push((child, segment));
+        is_map = matches!(child.kind, ValueKind::Map(_));
+        children = child.children();
+    }
+}
+
+/// Converts between byte offsets and LSP positions, which count UTF-16
+/// code units.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { text, starts }
+    }
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let offset = offset.min(self.text.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.text[self.starts[line]..offset].encode_utf16().count();
+        (line as u32, column as u32)
+    }
+    pub fn offset(&self, line: u32, character: u32) -> usize {
+        let Some(&start) = self.starts.get(line as usize) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (i, c) in self.text[start..].char_indices() {
+            if units >= character as usize || c == '\n' {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+}
@@ -0,0 +1,129 @@
+use serde_json::json;
+
+use super::*;
+use schema::Schema;
+
+const SCHEMA: &str = "
+    PizzaDto [map (
+        name string
+        sizes [array _SizeDto]
+        note [option string]
+    )]
+    SizeDto [map (
+        name string
+        price float
+    )]
+";
+
+#[test]
+fn line_index_test(){
+    let text = "a\n\u{1F355}b\nc";
+    let index = syntax::LineIndex::new(text);
+    assert_eq!(index.position(0), (0, 0));
+    assert_eq!(index.position(6), (1, 2));
+    assert_eq!(index.offset(1, 2), 6);
+    assert_eq!(index.offset(2, 5), text.len());
+    assert_eq!(index.position(text.len()), (2, 1));
+}
+
+#[test]
+fn schema_validation_test(){
+    let schema = Schema::parse(SCHEMA).unwrap();
+    let text = "{name margherita sizes [{name S price 6.99} {name M price cheap}] extra 1}";
+    let errors: Vec<String> = schema
+        .validate(&syntax::parse(text))
+        .into_iter()
+        .map(|(span, message)| format!("{}: {}", &text[span], message))
+        .collect();
+    assert_eq!(errors, vec![
+        "cheap: expected float, found string",
+        "extra: unknown key `extra`",
+    ]);
+    let errors = schema.validate(&syntax::parse("{sizes 1}"));
+    let messages: Vec<&str> = errors.iter().map(|(_, message)| message.as_str()).collect();
+    assert_eq!(messages, vec!["expected array of SizeDto, found integer", "missing key `name`"]);
+    assert_eq!(Schema::parse("A [map (x)]"), Err("expected a type".to_string()));
+
+    // Numbers too large or too precise for an `i128` or `f64` are still numbers.
+    let text = "{name x sizes [{name S price 1e400}] note 6.99}";
+    let errors = schema.validate(&syntax::parse(text));
+    let messages: Vec<&str> = errors.iter().map(|(_, message)| message.as_str()).collect();
+    assert_eq!(messages, vec!["expected string, found float"]);
+    let hover = |text: &str| features::hover(text, 1, None).unwrap()["contents"]["value"].clone();
+    assert_eq!(hover("1e400"), json!("`Expr::String`, or `Expr::Decimal` with exact `NumberOptions`"));
+    assert_eq!(hover("340282366920938463463374607431768211456"), json!("`Expr::Float`, or `Expr::BigInt` with exact `NumberOptions`"));
+    assert_eq!(hover("12"), json!("`Expr::Integer`"));
+}
+
+#[test]
+fn format_test(){
+    let text = "{name x  sizes [ {name S price 1}\n{name M price 2} ]}  [1 2]";
+    assert_eq!(
+        features::format(text, "  ").unwrap(),
+        "{\n  name x\n  sizes [\n    {name S price 1}\n    {name M price 2}\n  ]\n}\n[1 2]\n",
+    );
+    assert_eq!(features::format("[1 2", "  "), None);
+
+    // Width counts characters, not bytes.
+    let wide = format!("[{}]", ["żółć"; 15].join(" "));
+    assert_eq!(features::format(&wide, "  ").unwrap(), format!("{}\n", wide));
+
+    let text = "// pizzas\n{name x // the name\n sizes [{price 1 /* cents */}]} /* done */";
+    assert_eq!(
+        features::format(text, "  ").unwrap(),
+        "// pizzas\n{\n  name x // the name\n  sizes [\n    {\n      price 1 /* cents */\n    }\n  ]\n} /* done */\n",
+    );
+    assert_eq!(
+        features::format("{a // first\n 1 b 2}", "  ").unwrap(),
+        "{\n  a // first\n    1\n  b 2\n}\n",
+    );
+}
+
+#[test]
+fn completion_test(){
+    let schema = Schema::parse(SCHEMA).unwrap();
+    let text = "{name x sizes [{price 1 }]}";
+    let labels = |offset| -> Vec<String> {
+        features::completion(text, offset, &schema)
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(labels(text.find(" }").unwrap() + 1), vec!["name"]);
+    assert_eq!(labels(text.len() - 1), vec!["note"]);
+    assert_eq!(labels(6), Vec::<String>::new());
+}
+
+#[test]
+fn server_test(){
+    let mut server = server::Server::new();
+    let reply = server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }));
+    assert_eq!(reply[0]["result"]["capabilities"]["hoverProvider"], json!(true));
+
+    let uri = "file:///nonexistent/doc.pson";
+    let reply = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": uri, "languageId": "pson", "version": 1, "text": "{a\n  [1 2] b" } },
+    }));
+    let diagnostics = &reply[0]["params"]["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 2);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 1, "character": 9 }));
+    assert_eq!(diagnostics[0]["relatedInformation"][0]["location"]["range"]["start"], json!({ "line": 0, "character": 0 }));
+
+    let reply = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "textDocument/hover",
+        "params": { "textDocument": { "uri": uri }, "position": { "line": 1, "character": 4 } },
+    }));
+    assert_eq!(reply[0]["result"]["contents"]["value"], json!("`Expr::Integer`"));
+
+    let reply = server.handle(&json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/foldingRange",
+        "params": { "textDocument": { "uri": uri } } }));
+    assert_eq!(reply[0]["result"], json!([{ "startLine": 0, "endLine": 1 }]));
+
+    let reply = server.handle(&json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }));
+    assert_eq!(reply[0]["result"], json!(null));
+    assert!(server.shut_down());
+}
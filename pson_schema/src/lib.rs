@@ -96,7 +96,7 @@ fn pson_struct_tuple(token_trees: Vec<TokenTree>) -> PsonDef {
                 key.to_string().hash(&mut hasher);
                 let value = parse_pson_schema(value);
                 value.name.hash(&mut hasher);
-                let code = format!("{}:{}", key.to_string(), value.name);
+                let code = format!("{}:{}", key, value.name);
                 (value, code)
             })
             .fold(
@@ -202,7 +202,7 @@ pub fn pson_schemas(input: TokenStream) -> TokenStream {
     let iter_base = schema
         .iter()
         .map(|(name, object)| format!("type {}={};", name, object.name));
-    let iter_bodies = schema.iter().flat_map(|(_, object)| object.to_flat_iter());
+    let iter_bodies = schema.values().flat_map(|object| object.to_flat_iter());
     iter_base
         .chain(iter_bodies)
         .map(|s| {